@@ -1,4 +1,6 @@
+pub mod errors;
 pub(crate) mod interfaces;
+pub(crate) mod loopback_server;
 pub(crate) mod oauth2_service;
 pub mod youtube_repo;
 pub mod youtube_service;
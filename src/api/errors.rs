@@ -0,0 +1,163 @@
+use serde_json::Value;
+use std::{fmt, io, time::Duration};
+
+/// Errors returned by the YouTube Data API.
+#[derive(Debug)]
+pub enum YouTubeApiError {
+    /// The access token was rejected (HTTP 401 / `UNAUTHENTICATED`), e.g. because it expired.
+    Unauthorized(String),
+    /// Any other unsuccessful response.
+    Request { status: u16, body: String },
+}
+
+impl YouTubeApiError {
+    /// Builds the error for an unsuccessful response with the given status and body.
+    pub fn from_response(status: u16, body: String) -> Self {
+        let unauthenticated = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|json| {
+                json["error"]["status"]
+                    .as_str()
+                    .map(|s| s == "UNAUTHENTICATED")
+            })
+            .unwrap_or(false);
+
+        if status == 401 || unauthenticated {
+            Self::Unauthorized(body)
+        } else {
+            Self::Request { status, body }
+        }
+    }
+
+    /// Returns true when `error` is a [`YouTubeApiError::Request`] whose body gives `reason`
+    /// (e.g. `subscriptionDuplicate`) as the reason of one of its errors.
+    pub fn has_reason(error: &(dyn std::error::Error + 'static), reason: &str) -> bool {
+        let Some(YouTubeApiError::Request { body, .. }) = error.downcast_ref() else {
+            return false;
+        };
+
+        serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|json| {
+                json["error"]["errors"].as_array().map(|errors| {
+                    errors
+                        .iter()
+                        .any(|error| error["reason"].as_str() == Some(reason))
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns true when `error` is a [`YouTubeApiError::Unauthorized`].
+    pub fn is_unauthorized(error: &(dyn std::error::Error + 'static)) -> bool {
+        matches!(error.downcast_ref(), Some(YouTubeApiError::Unauthorized(_)))
+    }
+}
+
+impl fmt::Display for YouTubeApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unauthorized(body) => write!(f, "API request was not authorized: {body}"),
+            Self::Request { status, body } => {
+                write!(f, "API request failed with status {status}: {body}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for YouTubeApiError {}
+
+/// Errors ending the interactive OAuth authorization flow.
+#[derive(Debug)]
+pub enum OAuth2FlowError {
+    /// Google redirected back with an `error` parameter, e.g. `access_denied`.
+    Denied {
+        error: String,
+        description: Option<String>,
+    },
+    /// The returned `state` did not match the one we sent, the redirect may be forged.
+    StateMismatch,
+    /// The redirect had neither a `code` nor an `error` parameter.
+    MissingCode,
+    /// No redirect arrived in time.
+    Timeout(Duration),
+    Io(io::Error),
+}
+
+impl fmt::Display for OAuth2FlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Denied {
+                error,
+                description: Some(description),
+            } => write!(f, "Authorization failed: {error} ({description})"),
+            Self::Denied { error, .. } => write!(f, "Authorization failed: {error}"),
+            Self::StateMismatch => write!(
+                f,
+                "Authorization failed: the returned state does not match the one sent"
+            ),
+            Self::MissingCode => write!(f, "Authorization failed: no code was returned"),
+            Self::Timeout(timeout) => write!(
+                f,
+                "Authorization timed out after {}s waiting for the redirect",
+                timeout.as_secs()
+            ),
+            Self::Io(e) => write!(f, "Authorization redirect server failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for OAuth2FlowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for OAuth2FlowError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_response_detects_unauthenticated() {
+        let body = r#"{"error": {"code": 401, "status": "UNAUTHENTICATED"}}"#;
+
+        assert!(matches!(
+            YouTubeApiError::from_response(401, String::new()),
+            YouTubeApiError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            YouTubeApiError::from_response(403, body.to_string()),
+            YouTubeApiError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            YouTubeApiError::from_response(400, "{}".to_string()),
+            YouTubeApiError::Request { status: 400, .. }
+        ));
+    }
+
+    #[test]
+    fn has_reason_reads_the_error_reasons() {
+        let body = r#"{"error": {"code": 400, "errors": [{"reason": "subscriptionDuplicate"}]}}"#;
+        let duplicate = YouTubeApiError::from_response(400, body.to_string());
+        let other = YouTubeApiError::from_response(400, "not json".to_string());
+
+        assert!(YouTubeApiError::has_reason(
+            &duplicate,
+            "subscriptionDuplicate"
+        ));
+        assert!(!YouTubeApiError::has_reason(&duplicate, "quotaExceeded"));
+        assert!(!YouTubeApiError::has_reason(
+            &other,
+            "subscriptionDuplicate"
+        ));
+    }
+}
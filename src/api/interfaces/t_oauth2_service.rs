@@ -1,7 +1,32 @@
 use crate::models::oath_2::OauthSecrets;
 
 pub trait TOAuth2Service {
-    fn request_access_token(
+    /// Fills `secrets` with a usable access token granted `scopes`. A cached token is reused or
+    /// refreshed when possible; the interactive consent flow only runs when there is no usable
+    /// refresh token, or the cached one wasn't granted all of `scopes`.
+    async fn request_access_token(
+        &self,
         secrets: &mut OauthSecrets,
+        scopes: &[&str],
     ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>>;
+
+    /// Fills `secrets` with the cached access token, refreshed when expired, without ever asking
+    /// for consent. Returns `None` when there is no usable cached token.
+    async fn cached_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<Option<OauthSecrets>, Box<dyn std::error::Error + Send>>;
+
+    /// Exchanges the refresh token held in `secrets` for a new access token.
+    async fn refresh_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>>;
+
+    /// Revokes the cached tokens at the authorization server and deletes them locally. Returns
+    /// false when there were no cached tokens.
+    async fn revoke_token(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> anyhow::Result<bool, Box<dyn std::error::Error + Send>>;
 }
@@ -1,20 +1,60 @@
 use serde_json::Value;
 
-use crate::models::{oath_2::OauthSecrets, youtube::YouTubeChannel};
+use crate::models::{
+    channel_url::ChannelUrl,
+    oath_2::OauthSecrets,
+    secret::Secret,
+    youtube::{YouTubeChannel, YouTubeSubscription},
+};
 
 /// Methods for fetching data from the YouTube api.
 pub trait TYouTubeRepository {
     async fn fetch_videos(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel_id: &str,
         max_results: i32,
     ) -> anyhow::Result<Vec<Value>, Box<dyn std::error::Error>>;
 
     async fn subscribe(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel: &YouTubeChannel,
         secrets: &mut OauthSecrets,
     ) -> anyhow::Result<(), Box<dyn std::error::Error>>;
+
+    /// Deletes the subscription resource `subscription_id` of the account `secrets` belongs to.
+    async fn unsubscribe(
+        &self,
+        api_key: &Secret,
+        subscription_id: &str,
+        secrets: &OauthSecrets,
+    ) -> anyhow::Result<(), Box<dyn std::error::Error>>;
+
+    /// Fetches the channel of the account `secrets` belongs to.
+    async fn fetch_own_channel(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> anyhow::Result<YouTubeChannel, Box<dyn std::error::Error>>;
+
+    /// Fetches every subscription of the account `secrets` belongs to, page by page.
+    async fn fetch_subscriptions(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> anyhow::Result<Vec<YouTubeSubscription>, Box<dyn std::error::Error>>;
+
+    /// Looks up the id and title of the channel `channel_url` points at, `None` when there is
+    /// no such channel.
+    async fn fetch_channel(
+        &self,
+        api_key: &Secret,
+        channel_url: &ChannelUrl,
+    ) -> anyhow::Result<Option<YouTubeChannel>, Box<dyn std::error::Error>>;
+
+    /// Makes the cheapest request the API key allows, to check it is valid and has the YouTube
+    /// Data API enabled.
+    async fn check_api_key(
+        &self,
+        api_key: &Secret,
+    ) -> anyhow::Result<(), Box<dyn std::error::Error>>;
 }
@@ -2,13 +2,17 @@ use serde_json::Value;
 
 use crate::models::{
     oath_2::OauthSecrets,
-    youtube::{YouTubeChannel, YouTubeSubscriptionResult},
+    secret::Secret,
+    youtube::{
+        FailedYoutubeSubscription, YouTubeChannel, YouTubeSubscription, YouTubeSubscriptionPlan,
+        YouTubeSubscriptionResult, YouTubeSyncPlan,
+    },
 };
 
 pub trait TYouTubeService {
     async fn get_videos(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel_id: &str,
         max_results: i32,
     ) -> anyhow::Result<Vec<Value>, Box<dyn std::error::Error>>;
@@ -22,8 +26,75 @@ pub trait TYouTubeService {
 
     async fn subscribe(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channels: &[YouTubeChannel],
         secrets: &mut OauthSecrets,
     ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>>;
+
+    /// Carries out a plan from [`Self::plan_subscriptions`] as it is, without reading the
+    /// subscriptions again.
+    async fn apply_subscriptions(
+        &self,
+        api_key: &Secret,
+        plan: YouTubeSubscriptionPlan,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>>;
+
+    /// Unsubscribes from every channel in `channels` the account is subscribed to; the others
+    /// are reported as not subscribed.
+    async fn unsubscribe(
+        &self,
+        api_key: &Secret,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>>;
+
+    /// Deletes each of `subscriptions`, without reading the subscriptions again. The ones
+    /// already gone are reported as not subscribed.
+    async fn remove_subscriptions(
+        &self,
+        api_key: &Secret,
+        subscriptions: &[YouTubeSubscription],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>>;
+
+    /// Fetches the channel of the authenticated account.
+    async fn get_own_channel(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeChannel, Box<dyn std::error::Error>>;
+
+    /// Fetches every subscription of the authenticated account.
+    async fn get_subscriptions(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<Vec<YouTubeSubscription>, Box<dyn std::error::Error>>;
+
+    /// Works out what subscribing to `channels` would do, only reading from the account:
+    /// rows are normalized and deduplicated, and channels already subscribed to are set apart.
+    async fn plan_subscriptions(
+        &self,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionPlan, Box<dyn std::error::Error>>;
+
+    /// Works out how to converge the account towards `channels`, only reading from it: the
+    /// subscribe plan for `channels`, plus every subscription they don't list.
+    async fn plan_sync(
+        &self,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSyncPlan, Box<dyn std::error::Error>>;
+
+    /// Normalizes `channels`, and fills in the id (and the title, when missing) of every row
+    /// whose url doesn't hold the id by looking the channel up. Rows that can't be resolved are
+    /// left out, and come back with the reason instead.
+    async fn resolve_channels(
+        &self,
+        api_key: &Secret,
+        channels: Vec<YouTubeChannel>,
+    ) -> anyhow::Result<
+        (Vec<YouTubeChannel>, Vec<FailedYoutubeSubscription>),
+        Box<dyn std::error::Error>,
+    >;
 }
@@ -0,0 +1,320 @@
+use super::errors::OAuth2FlowError;
+use oauth2::{AuthorizationCode, CsrfToken};
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task::JoinSet,
+};
+use url::Url;
+
+const SUCCESS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head><meta charset="utf-8"><title>youtube-auto-sub</title></head>
+  <body style="font-family: sans-serif; text-align: center; margin-top: 4em">
+    <h1>Authorization complete</h1>
+    <p>You can close this tab and go back to your terminal.</p>
+  </body>
+</html>"#;
+
+const FAILURE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+  <head><meta charset="utf-8"><title>youtube-auto-sub</title></head>
+  <body style="font-family: sans-serif; text-align: center; margin-top: 4em">
+    <h1>Authorization failed</h1>
+    <p>{message}</p>
+    <p>Go back to your terminal for details.</p>
+  </body>
+</html>"#;
+
+/// Receives the OAuth redirect on a loopback address.
+pub struct LoopbackServer {
+    listener: TcpListener,
+    /// Only requests to this path are treated as the redirect, anything else (e.g.
+    /// `/favicon.ico`) gets a 404.
+    path: String,
+    /// Report responses that could not be written, which are otherwise ignored.
+    verbose: bool,
+}
+
+impl LoopbackServer {
+    pub async fn bind(addr: impl ToSocketAddrs, path: &str) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            path: path.to_string(),
+            verbose: false,
+        })
+    }
+
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Waits for the redirect and returns the authorization code it carries, once its `state`
+    /// has been checked against `expected_state`.
+    pub async fn receive(
+        &self,
+        expected_state: &CsrfToken,
+        timeout: Duration,
+    ) -> Result<AuthorizationCode, OAuth2FlowError> {
+        match tokio::time::timeout(timeout, self.serve(expected_state)).await {
+            Ok(result) => result,
+            Err(_) => Err(OAuth2FlowError::Timeout(timeout)),
+        }
+    }
+
+    async fn serve(
+        &self,
+        expected_state: &CsrfToken,
+    ) -> Result<AuthorizationCode, OAuth2FlowError> {
+        // Browsers open speculative connections that never send a request, so requests are read
+        // concurrently instead of one connection at a time.
+        let mut pending = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (stream, _) = accepted?;
+                    pending.spawn(read_request_target(stream));
+                }
+                Some(joined) = pending.join_next() => {
+                    let Ok(Ok((stream, target))) = joined else {
+                        continue;
+                    };
+
+                    if let Some(result) = self.handle(stream, &target, expected_state).await {
+                        return result;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Answers a single request. Returns `None` when the request was not the redirect.
+    ///
+    /// The page sent back is only a courtesy, so a browser that went away before reading it
+    /// doesn't lose the authorization code it already delivered.
+    async fn handle(
+        &self,
+        mut stream: TcpStream,
+        target: &str,
+        expected_state: &CsrfToken,
+    ) -> Option<Result<AuthorizationCode, OAuth2FlowError>> {
+        let url = match Url::parse(&("http://localhost".to_string() + target)) {
+            Ok(url) if url.path() == self.path => url,
+            _ => {
+                let written = respond(&mut stream, "404 Not Found", "Not Found").await;
+                self.report(written);
+                return None;
+            }
+        };
+
+        let result = parse_redirect(&url, expected_state);
+        let written = match &result {
+            Ok(_) => respond(&mut stream, "200 OK", SUCCESS_PAGE).await,
+            Err(e) => {
+                let page = FAILURE_PAGE.replace("{message}", &escape_html(&e.to_string()));
+                respond(&mut stream, "400 Bad Request", &page).await
+            }
+        };
+        self.report(written);
+
+        Some(result)
+    }
+
+    fn report(&self, written: io::Result<()>) {
+        if let Err(e) = written {
+            if self.verbose {
+                eprintln!("Could not answer the browser: {e}");
+            }
+        }
+    }
+}
+
+/// Extracts the authorization code from a redirect URL, validating its `state` and surfacing
+/// any `error` parameter.
+pub fn parse_redirect(
+    url: &Url,
+    expected_state: &CsrfToken,
+) -> Result<AuthorizationCode, OAuth2FlowError> {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    if let Some(error) = param("error") {
+        return Err(OAuth2FlowError::Denied {
+            error,
+            description: param("error_description"),
+        });
+    }
+
+    match param("state") {
+        Some(state) if state == *expected_state.secret() => {}
+        _ => return Err(OAuth2FlowError::StateMismatch),
+    }
+
+    param("code")
+        .map(AuthorizationCode::new)
+        .ok_or(OAuth2FlowError::MissingCode)
+}
+
+/// Reads the request line and headers, returning the request target (path and query).
+async fn read_request_target(mut stream: TcpStream) -> io::Result<(TcpStream, String)> {
+    let mut reader = BufReader::new(&mut stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // Drain the headers, so the browser doesn't see the connection reset before our response.
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+
+    Ok((stream, target))
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    async fn get(addr: SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn receive_ignores_unrelated_requests() {
+        // arrange
+        let sut = LoopbackServer::bind("127.0.0.1:0", "/callback")
+            .await
+            .unwrap();
+        let addr = sut.local_addr().unwrap();
+        let state = CsrfToken::new("expected".to_string());
+
+        // act
+        let client = tokio::spawn(async move {
+            // An idle connection, like a browser's speculative preconnect.
+            let _idle = TcpStream::connect(addr).await.unwrap();
+            let favicon = get(addr, "/favicon.ico").await;
+            let redirect = get(addr, "/callback?code=abc&state=expected").await;
+            (favicon, redirect)
+        });
+        let result = sut.receive(&state, Duration::from_secs(5)).await;
+        let (favicon, redirect) = client.await.unwrap();
+
+        // assert
+        assert_eq!(result.unwrap().secret(), "abc");
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        assert!(redirect.starts_with("HTTP/1.1 200"));
+        assert!(redirect.contains("Authorization complete"));
+    }
+
+    #[tokio::test]
+    async fn receive_rejects_state_mismatch() {
+        // arrange
+        let sut = LoopbackServer::bind("127.0.0.1:0", "/").await.unwrap();
+        let addr = sut.local_addr().unwrap();
+        let state = CsrfToken::new("expected".to_string());
+
+        // act
+        let client = tokio::spawn(async move { get(addr, "/?code=abc&state=forged").await });
+        let result = sut.receive(&state, Duration::from_secs(5)).await;
+        let response = client.await.unwrap();
+
+        // assert
+        assert!(matches!(result, Err(OAuth2FlowError::StateMismatch)));
+        assert!(response.starts_with("HTTP/1.1 400"));
+    }
+
+    #[tokio::test]
+    async fn receive_surfaces_oauth_errors() {
+        // arrange
+        let sut = LoopbackServer::bind("127.0.0.1:0", "/").await.unwrap();
+        let addr = sut.local_addr().unwrap();
+        let state = CsrfToken::new("expected".to_string());
+
+        // act
+        let client = tokio::spawn(async move { get(addr, "/?error=access_denied").await });
+        let result = sut.receive(&state, Duration::from_secs(5)).await;
+        let response = client.await.unwrap();
+
+        // assert
+        match result {
+            Err(OAuth2FlowError::Denied { error, .. }) => assert_eq!(error, "access_denied"),
+            _ => panic!("expected a denied error"),
+        }
+        assert!(response.contains("Authorization failed"));
+    }
+
+    #[tokio::test]
+    async fn handle_keeps_the_code_when_the_client_is_gone() {
+        // arrange
+        let sut = LoopbackServer::bind("127.0.0.1:0", "/callback")
+            .await
+            .unwrap();
+        let client = TcpStream::connect(sut.local_addr().unwrap()).await.unwrap();
+        let (stream, _) = sut.listener.accept().await.unwrap();
+        // Reset the connection instead of closing it, so writing the response fails.
+        client.set_zero_linger().unwrap();
+        drop(client);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let state = CsrfToken::new("expected".to_string());
+
+        // act
+        let result = sut
+            .handle(stream, "/callback?code=abc&state=expected", &state)
+            .await;
+
+        // assert
+        assert_eq!(result.unwrap().unwrap().secret(), "abc");
+    }
+
+    #[tokio::test]
+    async fn receive_times_out() {
+        let sut = LoopbackServer::bind("127.0.0.1:0", "/").await.unwrap();
+        let state = CsrfToken::new("expected".to_string());
+
+        let result = sut.receive(&state, Duration::from_millis(50)).await;
+
+        assert!(matches!(result, Err(OAuth2FlowError::Timeout(_))));
+    }
+}
@@ -1,133 +1,950 @@
-use crate::models::oath_2::OauthSecrets;
+use crate::{
+    consts,
+    models::oath_2::{unix_now, AuthFlow, OauthSecrets, StoredToken},
+    tools::{file_token_store::FileTokenStore, interfaces::t_token_store::TTokenStore},
+};
 use std::{
-    io::{BufRead, BufReader, Write},
-    net::TcpListener,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
 };
 
-use super::interfaces::t_oauth2_service::TOAuth2Service;
-use anyhow::anyhow;
-use oauth2::{basic::BasicClient, TokenResponse};
+use super::{
+    errors::OAuth2FlowError,
+    interfaces::t_oauth2_service::TOAuth2Service,
+    loopback_server::{parse_redirect, LoopbackServer},
+};
+use anyhow::{anyhow, Context};
+use oauth2::{
+    basic::{BasicClient, BasicErrorResponseType, BasicTokenResponse},
+    reqwest::async_http_client,
+    RequestTokenError, TokenResponse,
+};
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge, RedirectUrl,
+    devicecode::StandardDeviceAuthorizationResponse, AuthUrl, AuthorizationCode, ClientId,
+    ClientSecret, CsrfToken, DeviceAuthorizationUrl, PkceCodeChallenge, RedirectUrl, RefreshToken,
     Scope, TokenUrl,
 };
+use tokio::io::{AsyncBufReadExt, BufReader};
 use url::Url;
 
-#[derive(Default)]
-pub struct OAuth2Service {}
+const DEFAULT_REDIRECT_TIMEOUT: Duration = Duration::from_secs(300);
 
-impl TOAuth2Service for OAuth2Service {
-    fn request_access_token(
-        secrets: &mut OauthSecrets,
-    ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>> {
+pub struct OAuth2Service {
+    /// The account tokens are cached under.
+    account: String,
+    store: Box<dyn TTokenStore + Send + Sync>,
+    /// How long to wait for the browser to redirect back.
+    redirect_timeout: Duration,
+    /// Listen on a port picked by the OS instead of the one in the redirect url.
+    ephemeral_port: bool,
+    flow: AuthFlow,
+    /// Report detail that is otherwise left out, like a redirect page that couldn't be sent.
+    verbose: bool,
+}
+
+impl Default for OAuth2Service {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl OAuth2Service {
+    pub fn builder() -> OAuth2ServiceBuilder {
+        OAuth2ServiceBuilder::default()
+    }
+
+    fn build_client(secrets: &OauthSecrets) -> anyhow::Result<BasicClient> {
         let auth_url = match AuthUrl::new(secrets.auth_url.clone()) {
             Ok(url) => url,
             _ => {
-                return Err(anyhow!("Could not retrieve auth_url!").into());
+                return Err(anyhow!("Could not retrieve auth_url!"));
             }
         };
 
         let token_url = match TokenUrl::new(secrets.token_url.clone()) {
             Ok(url) => Some(url),
             _ => {
-                return Err(anyhow!("Could not retrieve token_url!").into());
+                return Err(anyhow!("Could not retrieve token_url!"));
             }
         };
 
         let client = BasicClient::new(
             ClientId::new(secrets.client_id.clone()),
-            Some(ClientSecret::new(secrets.client_secret.clone())),
-            // AuthUrl::new(secrets.auth_url.clone())?,
+            Some(ClientSecret::new(
+                secrets.client_secret.expose().to_string(),
+            )),
             auth_url,
             token_url,
-        )
-        .set_redirect_uri(
-            // RedirectUrl::new(secrets.redirect_url.clone()).expect("Invalid redirec url"),
-            RedirectUrl::new("http://localhost:8080".to_string()).expect("Invalid redirect url"),
         );
 
+        Ok(client)
+    }
+
+    /// Checks every url in `secrets` parses the way authorizing, refreshing and revoking parse
+    /// them, so a bad url is reported before anyone is sent to a browser.
+    pub fn validate_urls(secrets: &OauthSecrets) -> anyhow::Result<()> {
+        Self::build_client(secrets)?;
+        Url::parse(&secrets.redirect_url)
+            .with_context(|| format!("Invalid redirect url `{}`", secrets.redirect_url))?;
+        DeviceAuthorizationUrl::new(secrets.device_auth_url.clone()).with_context(|| {
+            format!(
+                "Invalid device authorization url `{}`",
+                secrets.device_auth_url
+            )
+        })?;
+        Url::parse(&secrets.revoke_url)
+            .with_context(|| format!("Invalid revocation url `{}`", secrets.revoke_url))?;
+
+        Ok(())
+    }
+
+    /// The address the loopback flow listens on for the redirect, or `None` when the flow
+    /// doesn't listen or lets the OS pick a free port.
+    pub fn loopback_addr(&self, secrets: &OauthSecrets) -> anyhow::Result<Option<SocketAddr>> {
+        if self.flow != AuthFlow::Loopback {
+            return Ok(None);
+        }
+
+        let redirect_url = Url::parse(&secrets.redirect_url)
+            .with_context(|| format!("Invalid redirect url `{}`", secrets.redirect_url))?;
+        let (bind_addr, ephemeral_port) = self.listen_addr(&secrets.redirect_url, &redirect_url)?;
+
+        Ok((!ephemeral_port).then_some(bind_addr))
+    }
+
+    /// The address to listen on for a redirect to `redirect_url`, parsed from `redirect_uri`,
+    /// and whether its port is left for the OS to pick.
+    fn listen_addr(
+        &self,
+        redirect_uri: &str,
+        redirect_url: &Url,
+    ) -> anyhow::Result<(SocketAddr, bool)> {
+        // Desktop clients are registered with a port-less `http://localhost`, which stands for
+        // any port rather than port 80. `Url` drops an explicit default port, so the original
+        // string tells the two apart.
+        let ephemeral_port = self.ephemeral_port || !has_explicit_port(redirect_uri);
+        let mut bind_addr = loopback_bind_addr(redirect_url)?;
+        if ephemeral_port {
+            bind_addr.set_port(0);
+        }
+
+        Ok((bind_addr, ephemeral_port))
+    }
+
+    /// Runs the interactive consent flow for `scopes` and stores the resulting token in
+    /// `secrets`.
+    async fn authorize(&self, secrets: &mut OauthSecrets, scopes: &[&str]) -> anyhow::Result<()> {
+        match self.flow {
+            AuthFlow::Device => self.authorize_device(secrets, scopes).await?,
+            AuthFlow::Loopback | AuthFlow::Manual => {
+                self.authorize_with_redirect(secrets, scopes).await?
+            }
+        }
+
+        eprintln!("Authorized. {}", secrets.token_summary());
+        Ok(())
+    }
+
+    /// Consent in a browser that redirects back with an authorization code.
+    async fn authorize_with_redirect(
+        &self,
+        secrets: &mut OauthSecrets,
+        scopes: &[&str],
+    ) -> anyhow::Result<()> {
+        let mut redirect_url = Url::parse(&secrets.redirect_url)
+            .with_context(|| format!("Invalid redirect url `{}`", secrets.redirect_url))?;
+        // Sent as registered, since `Url` would drop an explicit `:80`.
+        let mut redirect_uri = RedirectUrl::new(secrets.redirect_url.clone())
+            .with_context(|| format!("Invalid redirect url `{}`", secrets.redirect_url))?;
+
+        let server = match self.flow {
+            AuthFlow::Loopback => {
+                let (bind_addr, ephemeral_port) =
+                    self.listen_addr(&secrets.redirect_url, &redirect_url)?;
+
+                // Listen before handing out the URL, so a fast redirect can't beat us to it.
+                let server = LoopbackServer::bind(bind_addr, redirect_url.path())
+                    .await
+                    .with_context(|| {
+                        format!("Could not listen for the OAuth redirect on {bind_addr}")
+                    })?
+                    .with_verbose(self.verbose);
+
+                // Google accepts any port on a loopback redirect for desktop clients, so point
+                // the redirect at whichever port the OS picked.
+                if ephemeral_port {
+                    let port = server.local_addr()?.port();
+                    let _ = redirect_url.set_port(Some(port));
+                    redirect_uri = RedirectUrl::from_url(redirect_url);
+                }
+
+                Some(server)
+            }
+            // Nothing listens, the browser's failed redirect still shows the code in its url.
+            AuthFlow::Manual | AuthFlow::Device => None,
+        };
+
+        let client = Self::build_client(secrets)?.set_redirect_uri(redirect_uri);
+
         let (pkce_code_challenge, pkce_code_verifier) = PkceCodeChallenge::new_random_sha256();
 
         // Generate the full authorization URL.
         let (auth_url, csrf_state) = client
             .authorize_url(CsrfToken::new_random)
-            // Set the desired scopes.
-            // .add_scope(Scope::new("read".to_string()))
-            // .add_scope(Scope::new("write".to_string()))
-            .add_scope(Scope::new(
-                "https://www.googleapis.com/auth/youtube".to_string(),
-            ))
+            // Ask only for what the command needs, on top of whatever was granted before.
+            .add_scopes(scopes.iter().map(|scope| Scope::new(scope.to_string())))
+            .add_extra_param("include_granted_scopes", "true")
+            // Ask for a refresh token, and ask again even if the user consented before, since
+            // Google only hands one out on consent.
+            .add_extra_param("access_type", "offline")
+            .add_extra_param("prompt", "consent")
             // Set the PKCE code challenge.
             .set_pkce_challenge(pkce_code_challenge)
             .url();
 
         // This is the URL you should redirect the user to, in order to trigger the authorization
         // process.
-        println!("Browse to: {}", auth_url);
+        eprintln!("Browse to: {}", auth_url);
+
+        let code = match server {
+            Some(server) => server.receive(&csrf_state, self.redirect_timeout).await?,
+            None => {
+                eprintln!(
+                    "After approving, your browser is sent to a page that fails to load. \
+                     Paste its full url here (or just the `code` parameter):"
+                );
+
+                let mut input = String::new();
+                BufReader::new(tokio::io::stdin())
+                    .read_line(&mut input)
+                    .await?;
+                parse_pasted_redirect(&input, &csrf_state)?
+            }
+        };
+
+        // Exchange the code with a token.
+        let token_response = client
+            .exchange_code(code)
+            .set_pkce_verifier(pkce_code_verifier)
+            .request_async(async_http_client)
+            .await;
 
-        // let _ = std::io::stdin().read_line(&mut String::new());
-        let (code, state) = {
-            // A very naive implementation of the redirec server.
-            let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+        let token_response =
+            token_response.map_err(|e| anyhow!("Could not exchange the code: {e}"))?;
+        Self::apply_token_response(secrets, &token_response, scopes);
 
-            // The server will terminate itself after collecting the first code.
-            let Some(mut stream) = listener.incoming().flatten().next() else {
-                panic!("listener terminated without accepting a connection");
-            };
+        if secrets.access_token.is_empty() {
+            Err(anyhow!("Access token was not retrieved!"))
+        } else {
+            Ok(())
+        }
+    }
 
-            let mut reader = BufReader::new(&stream);
+    /// Consent through the device authorization grant: the user enters a short code on another
+    /// device while we poll the token endpoint.
+    async fn authorize_device(
+        &self,
+        secrets: &mut OauthSecrets,
+        scopes: &[&str],
+    ) -> anyhow::Result<()> {
+        let device_auth_url = DeviceAuthorizationUrl::new(secrets.device_auth_url.clone())
+            .with_context(|| {
+                format!(
+                    "Invalid device authorization url `{}`",
+                    secrets.device_auth_url
+                )
+            })?;
+        let client = Self::build_client(secrets)?.set_device_authorization_url(device_auth_url);
 
-            let mut request_line = String::new();
-            reader.read_line(&mut request_line).unwrap();
+        let details: StandardDeviceAuthorizationResponse = client
+            .exchange_device_code()?
+            .add_scopes(scopes.iter().map(|scope| Scope::new(scope.to_string())))
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| anyhow!("Could not start the device authorization: {e}"))?;
 
-            let redirect_url = request_line.split_whitespace().nth(1).unwrap();
-            let url = Url::parse(&("http://localhost".to_string() + redirect_url)).unwrap();
+        eprintln!(
+            "Open {} on any device and enter the code: {}",
+            details.verification_uri().as_str(),
+            details.user_code().secret()
+        );
 
-            let code = url
-                .query_pairs()
-                .find(|(key, _)| key == "code")
-                .map(|(_, code)| AuthorizationCode::new(code.into_owned()))
-                .unwrap();
+        let token_response = client
+            .exchange_device_access_token(&details)
+            .request_async(async_http_client, tokio::time::sleep, None)
+            .await
+            .map_err(|e| match e {
+                RequestTokenError::ServerResponse(e) => anyhow!(OAuth2FlowError::Denied {
+                    error: e.error().to_string(),
+                    description: e.error_description().cloned(),
+                }),
+                e => anyhow!("Could not complete the device authorization: {e}"),
+            })?;
+        Self::apply_token_response(secrets, &token_response, scopes);
 
-            let state = url
-                .query_pairs()
-                .find(|(key, _)| key == "state")
-                .map(|(_, state)| CsrfToken::new(state.into_owned()))
-                .unwrap();
+        Ok(())
+    }
 
-            let message = "Go back to your terminal :)";
-            let response = format!(
-                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
-                message.len(),
-                message
-            );
+    /// Exchanges the refresh token for a new access token. Returns `Ok(false)` when Google
+    /// rejected the refresh token as revoked or expired.
+    async fn try_refresh(&self, secrets: &mut OauthSecrets) -> anyhow::Result<bool> {
+        let Some(refresh_token) = secrets.refresh_token.clone() else {
+            return Ok(false);
+        };
 
-            stream.write_all(response.as_bytes()).unwrap();
+        let client = Self::build_client(secrets)?;
+        let refresh_token = RefreshToken::new(refresh_token.expose().to_string());
+        let token_response = client
+            .exchange_refresh_token(&refresh_token)
+            .request_async(async_http_client)
+            .await;
 
-            (code, state)
+        match token_response {
+            Ok(token_response) => {
+                // A refresh grants nothing new.
+                Self::apply_token_response(secrets, &token_response, &[]);
+                self.store
+                    .save(&self.account, &StoredToken::from(&*secrets))?;
+                Ok(true)
+            }
+            Err(RequestTokenError::ServerResponse(e))
+                if *e.error() == BasicErrorResponseType::InvalidGrant =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(anyhow!("Could not refresh the access token: {e}")),
+        }
+    }
+
+    /// Copies a token response into `secrets`. When the response doesn't list the granted scopes,
+    /// the ones granted before plus `requested` are assumed, as consent asks for
+    /// `include_granted_scopes`.
+    fn apply_token_response(
+        secrets: &mut OauthSecrets,
+        token_response: &BasicTokenResponse,
+        requested: &[&str],
+    ) {
+        secrets.access_token = token_response.access_token().secret().as_str().into();
+        // Google usually omits the refresh token when refreshing, keep the one we have.
+        if let Some(refresh_token) = token_response.refresh_token() {
+            secrets.refresh_token = Some(refresh_token.secret().as_str().into());
+        }
+        secrets.expires_at = token_response
+            .expires_in()
+            .map(|expires_in| unix_now() + expires_in.as_secs());
+        secrets.scopes = match token_response.scopes() {
+            Some(scopes) => scopes.iter().map(|scope| scope.to_string()).collect(),
+            None => {
+                let mut scopes = secrets.scopes.clone();
+                for scope in requested {
+                    if !scopes.iter().any(|granted| granted == scope) {
+                        scopes.push(scope.to_string());
+                    }
+                }
+                scopes
+            }
         };
+    }
+}
 
-        println!("Google returned the following code:\n{}\n", code.secret());
-        println!(
-            "Google returned the following state:\n{} (expected `{}`)\n",
-            state.secret(),
-            csrf_state.secret()
-        );
+impl TOAuth2Service for OAuth2Service {
+    async fn request_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+        scopes: &[&str],
+    ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>> {
+        if let Some(token) = self.store.load(&self.account)? {
+            secrets.set_token(token);
 
-        // Exchange the code with a token.
-        let token_response = client
-            .exchange_code(code)
-            .set_pkce_verifier(pkce_code_verifier)
-            .request(oauth2::reqwest::http_client);
+            if !secrets.has_scopes(scopes) {
+                eprintln!(
+                    "This command needs more access than was granted before, authorizing again."
+                );
+            } else if !secrets.is_access_token_expired() || self.try_refresh(secrets).await? {
+                return Ok(secrets.clone());
+            } else if secrets.refresh_token.is_some() {
+                eprintln!("The cached refresh token was revoked, authorizing again.");
+            }
+        }
 
-        println!("Google returned the following token:\n{token_response:?}\n");
+        self.authorize(secrets, scopes).await?;
+        self.store
+            .save(&self.account, &StoredToken::from(&*secrets))?;
 
-        let access_token = token_response.unwrap().access_token().secret().to_string();
-        secrets.access_token = access_token;
-        if secrets.access_token.is_empty() {
-            Err(anyhow!("Access token was not retrieved!").into())
+        Ok(secrets.clone())
+    }
+
+    async fn cached_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<Option<OauthSecrets>, Box<dyn std::error::Error + Send>> {
+        let Some(token) = self.store.load(&self.account)? else {
+            return Ok(None);
+        };
+
+        secrets.set_token(token);
+        if !secrets.is_access_token_expired() || self.try_refresh(secrets).await? {
+            Ok(Some(secrets.clone()))
         } else {
+            Ok(None)
+        }
+    }
+
+    async fn refresh_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>> {
+        if self.try_refresh(secrets).await? {
             Ok(secrets.clone())
+        } else {
+            Err(anyhow!("No valid refresh token for account `{}`", self.account).into())
         }
     }
+
+    async fn revoke_token(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> anyhow::Result<bool, Box<dyn std::error::Error + Send>> {
+        let Some(token) = self.store.load(&self.account)? else {
+            return Ok(false);
+        };
+
+        // Revoking the refresh token also revokes the access tokens issued for it.
+        let revocable = token.refresh_token.unwrap_or(token.access_token);
+        revoke(&secrets.revoke_url, revocable.expose()).await?;
+
+        self.store.delete(&self.account)?;
+        Ok(true)
+    }
+}
+
+/// Revokes `token` at the revocation endpoint. oauth2's own revocation request insists on https,
+/// which rules out a local stand-in for the endpoint, so the form is posted directly.
+async fn revoke(url: &str, token: &str) -> anyhow::Result<()> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .form(&[("token", token)])
+        .send()
+        .await
+        .context("Could not reach the revocation endpoint")?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let error = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| json["error"].as_str().map(str::to_string));
+    match error.as_deref() {
+        // The token already expired or was revoked elsewhere, there is nothing left to undo.
+        Some("invalid_token") => Ok(()),
+        _ => Err(anyhow!("Could not revoke the token: {status} {body}")),
+    }
+}
+
+/// The local address to listen on for a redirect to `redirect_url`, which must point at the
+/// loopback interface over plain http.
+fn loopback_bind_addr(redirect_url: &Url) -> anyhow::Result<SocketAddr> {
+    if redirect_url.scheme() != "http" {
+        return Err(anyhow!(
+            "Redirect url `{redirect_url}` must use http, the redirect is received locally"
+        ));
+    }
+
+    let ip = match redirect_url.host() {
+        Some(url::Host::Domain("localhost")) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Some(url::Host::Ipv4(ip)) if ip.is_loopback() => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) if ip == Ipv6Addr::LOCALHOST => IpAddr::V6(ip),
+        _ => {
+            return Err(anyhow!(
+                "Redirect url `{redirect_url}` must point at localhost or a loopback address"
+            ))
+        }
+    };
+    let port = redirect_url.port_or_known_default().unwrap_or(80);
+
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// Whether `uri` spells out a port, even the scheme's default one.
+fn has_explicit_port(uri: &str) -> bool {
+    let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // An IPv6 host holds colons of its own, the port follows its closing bracket.
+    let after_host = match host_port.rfind(']') {
+        Some(end) => &host_port[end + 1..],
+        None => host_port,
+    };
+
+    after_host
+        .split_once(':')
+        .is_some_and(|(_, port)| !port.is_empty())
+}
+
+/// Extracts the authorization code from what the user pasted: the full redirect url, its query
+/// string, or the bare code. The state is checked whenever it was pasted along.
+fn parse_pasted_redirect(
+    input: &str,
+    expected_state: &CsrfToken,
+) -> Result<AuthorizationCode, OAuth2FlowError> {
+    let input = input.trim();
+
+    let url = if input.contains("://") {
+        Url::parse(input).ok()
+    } else if input.contains("code=") || input.contains("error=") {
+        Url::parse(&format!(
+            "http://localhost/?{}",
+            input.trim_start_matches('?')
+        ))
+        .ok()
+    } else {
+        None
+    };
+
+    match url {
+        Some(url) => parse_redirect(&url, expected_state),
+        None if input.is_empty() => Err(OAuth2FlowError::MissingCode),
+        None => Ok(AuthorizationCode::new(input.to_string())),
+    }
+}
+
+#[derive(Default)]
+pub struct OAuth2ServiceBuilder {
+    account: Option<String>,
+    store: Option<Box<dyn TTokenStore + Send + Sync>>,
+    redirect_timeout: Option<Duration>,
+    ephemeral_port: bool,
+    flow: AuthFlow,
+    verbose: bool,
+}
+
+impl OAuth2ServiceBuilder {
+    pub fn with_account(mut self, account: &str) -> OAuth2ServiceBuilder {
+        self.account = Some(account.to_string());
+        self
+    }
+
+    pub fn with_store(mut self, store: Box<dyn TTokenStore + Send + Sync>) -> OAuth2ServiceBuilder {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_redirect_timeout(mut self, timeout: Duration) -> OAuth2ServiceBuilder {
+        self.redirect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_ephemeral_port(mut self, ephemeral_port: bool) -> OAuth2ServiceBuilder {
+        self.ephemeral_port = ephemeral_port;
+        self
+    }
+
+    pub fn with_flow(mut self, flow: AuthFlow) -> OAuth2ServiceBuilder {
+        self.flow = flow;
+        self
+    }
+
+    pub fn with_verbose(mut self, verbose: bool) -> OAuth2ServiceBuilder {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn build(self) -> OAuth2Service {
+        OAuth2Service {
+            account: self
+                .account
+                .unwrap_or_else(|| consts::DEFAULT_ACCOUNT.to_string()),
+            store: self
+                .store
+                .unwrap_or_else(|| Box::new(FileTokenStore::default())),
+            redirect_timeout: self.redirect_timeout.unwrap_or(DEFAULT_REDIRECT_TIMEOUT),
+            ephemeral_port: self.ephemeral_port,
+            flow: self.flow,
+            verbose: self.verbose,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::secret::Secret;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn loopback_bind_addr_follows_redirect_url() {
+        let bind_addr = |url: &str| loopback_bind_addr(&Url::parse(url).unwrap());
+
+        assert_eq!(
+            bind_addr("http://localhost:8080").unwrap(),
+            "127.0.0.1:8080".parse().unwrap()
+        );
+        assert_eq!(
+            bind_addr("http://127.0.0.1:9004/callback").unwrap(),
+            "127.0.0.1:9004".parse().unwrap()
+        );
+        assert_eq!(
+            bind_addr("http://[::1]").unwrap(),
+            "[::1]:80".parse().unwrap()
+        );
+        assert!(bind_addr("https://localhost:8080").is_err());
+        assert!(bind_addr("http://example.com:8080").is_err());
+    }
+
+    #[test]
+    fn loopback_addr_is_only_fixed_for_a_loopback_flow_with_a_port() {
+        let secrets = |redirect_url: &str| OauthSecrets {
+            redirect_url: redirect_url.to_string(),
+            ..Default::default()
+        };
+        let loopback = OAuth2Service::default();
+        let ephemeral = OAuth2Service::builder().with_ephemeral_port(true).build();
+        let device = OAuth2Service::builder().with_flow(AuthFlow::Device).build();
+
+        assert_eq!(
+            loopback
+                .loopback_addr(&secrets("http://localhost:8080"))
+                .unwrap(),
+            Some("127.0.0.1:8080".parse().unwrap())
+        );
+        assert_eq!(
+            loopback
+                .loopback_addr(&secrets("http://localhost"))
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            loopback
+                .loopback_addr(&secrets("http://localhost:80"))
+                .unwrap(),
+            Some("127.0.0.1:80".parse().unwrap())
+        );
+        assert_eq!(
+            loopback
+                .loopback_addr(&secrets("http://[::1]:80/cb"))
+                .unwrap(),
+            Some("[::1]:80".parse().unwrap())
+        );
+        assert_eq!(
+            loopback.loopback_addr(&secrets("http://[::1]/cb")).unwrap(),
+            None
+        );
+        assert_eq!(
+            ephemeral
+                .loopback_addr(&secrets("http://localhost:8080"))
+                .unwrap(),
+            None
+        );
+        assert_eq!(device.loopback_addr(&secrets("not a url")).unwrap(), None);
+    }
+
+    #[test]
+    fn parse_pasted_redirect_accepts_url_query_or_code() {
+        let state = CsrfToken::new("expected".to_string());
+        let parse = |input: &str| parse_pasted_redirect(input, &state);
+
+        let from_url = parse("http://localhost:8080/?state=expected&code=4/abc&scope=x\n");
+        assert_eq!(from_url.unwrap().secret(), "4/abc");
+        let from_query = parse("?code=4/abc&state=expected");
+        assert_eq!(from_query.unwrap().secret(), "4/abc");
+        assert_eq!(parse(" 4/abc ").unwrap().secret(), "4/abc");
+    }
+
+    #[test]
+    fn parse_pasted_redirect_applies_state_and_error_checks() {
+        let state = CsrfToken::new("expected".to_string());
+        let parse = |input: &str| parse_pasted_redirect(input, &state);
+
+        assert!(matches!(
+            parse("http://localhost:8080/?state=forged&code=abc"),
+            Err(OAuth2FlowError::StateMismatch)
+        ));
+        assert!(matches!(
+            parse("http://localhost:8080/?error=access_denied"),
+            Err(OAuth2FlowError::Denied { .. })
+        ));
+        assert!(matches!(parse("\n"), Err(OAuth2FlowError::MissingCode)));
+    }
+
+    #[tokio::test]
+    async fn device_flow_polls_until_authorized_and_caches_token() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/device/code"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-code",
+                "user_code": "ABC-DEF",
+                "verification_url": "https://www.google.com/device",
+                "expires_in": 1800,
+                "interval": 0
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("device_code=device-code"))
+            .respond_with(
+                ResponseTemplate::new(428).set_body_json(json!({"error": "authorization_pending"})),
+            )
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "access",
+                "refresh_token": "refresh",
+                "token_type": "Bearer",
+                "expires_in": 3599,
+                "scope": consts::YOUTUBE_SCOPE
+            })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let sut = OAuth2Service::builder()
+            .with_flow(AuthFlow::Device)
+            .with_store(Box::new(FileTokenStore::new(dir.path().to_path_buf())))
+            .build();
+        let mut secrets = OauthSecrets {
+            client_id: "client".to_string(),
+            client_secret: "secret".into(),
+            auth_url: format!("{}/auth", server.uri()),
+            token_url: format!("{}/token", server.uri()),
+            device_auth_url: format!("{}/device/code", server.uri()),
+            ..Default::default()
+        };
+
+        // act
+        let result = sut
+            .request_access_token(&mut secrets, &[consts::YOUTUBE_SCOPE])
+            .await;
+
+        // assert
+        assert!(result.is_ok());
+        assert_eq!(secrets.access_token.expose(), "access");
+        assert_eq!(
+            secrets.refresh_token.as_ref().map(Secret::expose),
+            Some("refresh")
+        );
+        let cached = sut.store.load(consts::DEFAULT_ACCOUNT).unwrap().unwrap();
+        assert_eq!(cached.access_token.expose(), "access");
+    }
+
+    #[tokio::test]
+    async fn request_access_token_reauthorizes_when_cached_scopes_fall_short() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/device/code"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-code",
+                "user_code": "ABC-DEF",
+                "verification_url": "https://www.google.com/device",
+                "expires_in": 1800,
+                "interval": 0
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "full-access",
+                "token_type": "Bearer",
+                "expires_in": 3599,
+                "scope": format!("{} {}", consts::YOUTUBE_READONLY_SCOPE, consts::YOUTUBE_SCOPE)
+            })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileTokenStore::new(dir.path().to_path_buf());
+        let readonly = StoredToken {
+            access_token: "readonly-access".into(),
+            scopes: vec![consts::YOUTUBE_READONLY_SCOPE.to_string()],
+            ..Default::default()
+        };
+        store.save(consts::DEFAULT_ACCOUNT, &readonly).unwrap();
+        let sut = OAuth2Service::builder()
+            .with_flow(AuthFlow::Device)
+            .with_store(Box::new(store))
+            .build();
+        let mut secrets = OauthSecrets {
+            auth_url: format!("{}/auth", server.uri()),
+            token_url: format!("{}/token", server.uri()),
+            device_auth_url: format!("{}/device/code", server.uri()),
+            ..Default::default()
+        };
+
+        // act
+        let reused = sut
+            .request_access_token(&mut secrets, &[consts::YOUTUBE_READONLY_SCOPE])
+            .await
+            .unwrap();
+        let broadened = sut
+            .request_access_token(&mut secrets, &[consts::YOUTUBE_SCOPE])
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(reused.access_token.expose(), "readonly-access");
+        assert_eq!(broadened.access_token.expose(), "full-access");
+        assert!(broadened.has_scopes(&[consts::YOUTUBE_SCOPE]));
+    }
+
+    #[tokio::test]
+    async fn authorizing_keeps_earlier_scopes_when_the_response_lists_none() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/device/code"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-code",
+                "user_code": "ABC-DEF",
+                "verification_url": "https://www.google.com/device",
+                "expires_in": 1800,
+                "interval": 0
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "full-access",
+                "token_type": "Bearer",
+                "expires_in": 3599
+            })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileTokenStore::new(dir.path().to_path_buf());
+        let readonly = StoredToken {
+            access_token: "readonly-access".into(),
+            scopes: vec![consts::YOUTUBE_READONLY_SCOPE.to_string()],
+            ..Default::default()
+        };
+        store.save(consts::DEFAULT_ACCOUNT, &readonly).unwrap();
+        let sut = OAuth2Service::builder()
+            .with_flow(AuthFlow::Device)
+            .with_store(Box::new(store))
+            .build();
+        let mut secrets = OauthSecrets {
+            auth_url: format!("{}/auth", server.uri()),
+            token_url: format!("{}/token", server.uri()),
+            device_auth_url: format!("{}/device/code", server.uri()),
+            ..Default::default()
+        };
+
+        // act
+        let broadened = sut
+            .request_access_token(&mut secrets, &[consts::YOUTUBE_SCOPE])
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(
+            broadened.scopes,
+            vec![consts::YOUTUBE_READONLY_SCOPE, consts::YOUTUBE_SCOPE]
+        );
+        let cached = sut.store.load(consts::DEFAULT_ACCOUNT).unwrap().unwrap();
+        assert_eq!(cached.scopes, broadened.scopes);
+    }
+
+    #[tokio::test]
+    async fn device_flow_surfaces_denied_consent() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/device/code"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "device_code": "device-code",
+                "user_code": "ABC-DEF",
+                "verification_url": "https://www.google.com/device",
+                "expires_in": 1800,
+                "interval": 0
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(
+                ResponseTemplate::new(403).set_body_json(json!({"error": "access_denied"})),
+            )
+            .mount(&server)
+            .await;
+
+        let sut = OAuth2Service::builder().with_flow(AuthFlow::Device).build();
+        let mut secrets = OauthSecrets {
+            auth_url: format!("{}/auth", server.uri()),
+            token_url: format!("{}/token", server.uri()),
+            device_auth_url: format!("{}/device/code", server.uri()),
+            ..Default::default()
+        };
+
+        // act
+        let result = sut
+            .authorize(&mut secrets, &[consts::YOUTUBE_READONLY_SCOPE])
+            .await;
+
+        // assert
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OAuth2FlowError>(),
+            Some(OAuth2FlowError::Denied { error, .. }) if error == "access_denied"
+        ));
+    }
+
+    #[tokio::test]
+    async fn revoke_token_revokes_refresh_token_and_deletes_cache() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/revoke"))
+            .and(body_string_contains("token=refresh"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileTokenStore::new(dir.path().to_path_buf());
+        let token = StoredToken {
+            access_token: "access".into(),
+            refresh_token: Some("refresh".into()),
+            ..Default::default()
+        };
+        store.save("work", &token).unwrap();
+        let sut = OAuth2Service::builder()
+            .with_account("work")
+            .with_store(Box::new(store))
+            .build();
+        let secrets = OauthSecrets {
+            auth_url: format!("{}/auth", server.uri()),
+            token_url: format!("{}/token", server.uri()),
+            revoke_url: format!("{}/revoke", server.uri()),
+            ..Default::default()
+        };
+
+        // act
+        let revoked = sut.revoke_token(&secrets).await;
+        let revoked_again = sut.revoke_token(&secrets).await;
+
+        // assert
+        assert!(revoked.unwrap());
+        assert!(!revoked_again.unwrap());
+        assert!(sut.store.load("work").unwrap().is_none());
+    }
 }
@@ -1,13 +1,153 @@
 use crate::{
-    api::interfaces::t_youtube_repository::TYouTubeRepository,
-    models::{oath_2::OauthSecrets, youtube::YouTubeChannel},
+    api::{errors::YouTubeApiError, interfaces::t_youtube_repository::TYouTubeRepository},
+    consts,
+    models::{
+        channel_url::ChannelUrl,
+        oath_2::OauthSecrets,
+        secret::Secret,
+        youtube::{YouTubeChannel, YouTubeSubscription},
+    },
+    tools::interfaces::t_channel_cache::TChannelCache,
 };
 // use anyhow::Ok;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU32, Ordering};
 
-#[derive(Default)]
-pub struct YouTubeRepository {}
+/// Header Google APIs accept the API key in, instead of the `key` query parameter.
+const API_KEY_HEADER: &str = "x-goog-api-key";
+/// Channel looked up to check an API key works (Google for Developers).
+const PROBE_CHANNEL_ID: &str = "UC_x5XG1OV2P6uZZ5FSM9Ttw";
+
+pub struct YouTubeRepository {
+    /// Root of the YouTube Data API, without a trailing slash.
+    base_url: String,
+    /// Consulted before looking channels up, and kept up to date with every lookup.
+    channel_cache: Option<Box<dyn TChannelCache + Send + Sync>>,
+    /// Quota units spent looking channels up so far.
+    lookup_cost: AtomicU32,
+}
+
+impl Default for YouTubeRepository {
+    fn default() -> Self {
+        Self::new("https://www.googleapis.com/youtube/v3")
+    }
+}
+
+impl YouTubeRepository {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            channel_cache: None,
+            lookup_cost: AtomicU32::new(0),
+        }
+    }
+
+    /// Quota units spent looking channels up so far; lookups the cache answered cost none.
+    pub fn lookup_cost(&self) -> u32 {
+        self.lookup_cost.load(Ordering::Relaxed)
+    }
+
+    /// Caches channel lookups in `channel_cache`.
+    pub fn with_channel_cache(
+        mut self,
+        channel_cache: impl TChannelCache + Send + Sync + 'static,
+    ) -> Self {
+        self.channel_cache = Some(Box::new(channel_cache));
+        self
+    }
+
+    /// GETs `{base_url}/{path}` with `query` and the API key, returning the parsed body.
+    async fn get_public(
+        &self,
+        api_key: &Secret,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let response = Client::new()
+            .get(format!("{}/{path}", self.base_url))
+            .query(query)
+            .header(API_KEY_HEADER, api_key.expose())
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        // Check if the response was successful
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Looks the channel `channel_url` points at up in the API; every request costs one quota
+    /// unit.
+    async fn lookup_channel(
+        &self,
+        api_key: &Secret,
+        channel_url: &ChannelUrl,
+    ) -> Result<Option<YouTubeChannel>, Box<dyn std::error::Error>> {
+        let queries = match channel_url {
+            ChannelUrl::Id(id) => vec![("id", id.clone())],
+            ChannelUrl::Video(id) => {
+                self.lookup_cost
+                    .fetch_add(consts::LIST_QUOTA_COST, Ordering::Relaxed);
+                let json = self
+                    .get_public(api_key, "videos", &[("part", "snippet"), ("id", id)])
+                    .await?;
+                let snippet = &json["items"][0]["snippet"];
+                return Ok(snippet["channelId"].as_str().map(|channel_id| {
+                    channel(
+                        channel_id,
+                        snippet["channelTitle"].as_str().unwrap_or_default(),
+                    )
+                }));
+            }
+            ChannelUrl::Handle(handle) => vec![("forHandle", format!("@{handle}"))],
+            ChannelUrl::Username(name) => vec![("forUsername", name.clone())],
+            // There is no lookup by custom url, but most custom names live on as the handle,
+            // and the oldest ones are usernames.
+            ChannelUrl::CustomName(name) => {
+                vec![
+                    ("forHandle", format!("@{name}")),
+                    ("forUsername", name.clone()),
+                ]
+            }
+        };
+
+        for (filter, value) in queries {
+            self.lookup_cost
+                .fetch_add(consts::LIST_QUOTA_COST, Ordering::Relaxed);
+            let json = self
+                .get_public(
+                    api_key,
+                    "channels",
+                    &[("part", "snippet"), (filter, value.as_str())],
+                )
+                .await?;
+            let item = &json["items"][0];
+            if let Some(channel_id) = item["id"].as_str() {
+                return Ok(Some(channel(
+                    channel_id,
+                    item["snippet"]["title"].as_str().unwrap_or_default(),
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The channel `channel_id`, titled `channel_title`, in the shape input files list it in.
+fn channel(channel_id: &str, channel_title: &str) -> YouTubeChannel {
+    YouTubeChannel {
+        channel_url: ChannelUrl::Id(channel_id.to_string()).canonical(),
+        channel_id: channel_id.to_string(),
+        channel_title: channel_title.to_string(),
+    }
+}
 
 // Implement the Repository for the YouTube service
 impl TYouTubeRepository for YouTubeRepository {
@@ -18,35 +158,36 @@ impl TYouTubeRepository for YouTubeRepository {
     ///  `max_results`: Max number of videos to fetch.
     async fn fetch_videos(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel_id: &str,
         max_results: i32,
     ) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
         let mut videos: Vec<Value> = Vec::new();
         let mut page_token = String::new();
+        let max_results = max_results.max(0) as usize;
 
-        loop {
-            if videos.len() > max_results as usize {
-                break;
-            }
-
-            // build the api request url
+        while videos.len() < max_results {
+            // build the api request url, pages hold at most 50 results
             let url = format!(
-            "https://www.googleapis.com/youtube/v3/search?key={}&channelId={}&part=snippet,id&order=date&maxResults={}&type=video&pageToken={}",
-            api_key,
+            "{}/search?channelId={}&part=snippet,id&order=date&maxResults={}&type=video&pageToken={}",
+            self.base_url,
             channel_id,
-            max_results,
+            (max_results - videos.len()).min(50),
             page_token);
 
             // Is there a way to abstract out this Client::new() dependency?
-            let response = Client::new().get(&url).send().await?;
+            // The key goes in a header, so it can't leak through urls in error messages.
+            let response = Client::new()
+                .get(&url)
+                .header(API_KEY_HEADER, api_key.expose())
+                .send()
+                .await?;
 
             // Check if the response was successful
-            if !response.status().is_success() {
-                println!("API request failed with status: {}", response.status());
-                println!("Response body: {}", response.text().await?);
-
-                return Err("API request failed".into());
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await?;
+                return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
             }
 
             // Parse the response body
@@ -71,20 +212,18 @@ impl TYouTubeRepository for YouTubeRepository {
             }
         }
 
+        videos.truncate(max_results);
         Ok(videos)
     }
 
     async fn subscribe(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel: &YouTubeChannel,
         secrets: &mut OauthSecrets,
     ) -> Result<(), Box<dyn std::error::Error>> {
         // build the api request url
-        let url = format!(
-            "https://youtube.googleapis.com/youtube/v3/subscriptions?part=snippet&key={} HTTP/1.1",
-            api_key
-        );
+        let url = format!("{}/subscriptions?part=snippet", self.base_url);
         // let url_old = format!(
         //     r#"POST https://youtube.googleapis.com/youtube/v3/subscriptions?part=snippet&key={} HTTP/1.1
 
@@ -114,7 +253,8 @@ impl TYouTubeRepository for YouTubeRepository {
         // Is there a way to abstract out this Client::new() dependency?
         let response = Client::new()
             .post(&url)
-            .header("Authorization", format!("Bearer {}", secrets.access_token))
+            .header(API_KEY_HEADER, api_key.expose())
+            .bearer_auth(secrets.access_token.expose())
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
             .json(&json_payload)
@@ -122,11 +262,10 @@ impl TYouTubeRepository for YouTubeRepository {
             .await?;
 
         // Check if the response was successful
-        if !response.status().is_success() {
-            println!("API request failed with status: {}", response.status());
-            println!("Response body: {}", response.text().await?);
-
-            return Err("API request failed".into());
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
         }
 
         // Parse the response body
@@ -139,4 +278,476 @@ impl TYouTubeRepository for YouTubeRepository {
             Ok(())
         }
     }
+
+    async fn unsubscribe(
+        &self,
+        api_key: &Secret,
+        subscription_id: &str,
+        secrets: &OauthSecrets,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let response = Client::new()
+            .delete(format!("{}/subscriptions", self.base_url))
+            .query(&[("id", subscription_id)])
+            .header(API_KEY_HEADER, api_key.expose())
+            .bearer_auth(secrets.access_token.expose())
+            .send()
+            .await?;
+
+        // A successful delete answers 204 without a body.
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_own_channel(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> Result<YouTubeChannel, Box<dyn std::error::Error>> {
+        let response = Client::new()
+            .get(format!("{}/channels?part=snippet&mine=true", self.base_url))
+            .bearer_auth(secrets.access_token.expose())
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        // Check if the response was successful
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
+        }
+
+        let json: Value = response.json().await?;
+        let Some(channel) = json["items"].get(0) else {
+            return Err("This Google account has no YouTube channel".into());
+        };
+
+        let channel_id = channel["id"].as_str().unwrap_or_default().to_string();
+        Ok(YouTubeChannel {
+            channel_url: format!("https://www.youtube.com/channel/{channel_id}"),
+            channel_id,
+            channel_title: channel["snippet"]["title"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    async fn fetch_subscriptions(
+        &self,
+        secrets: &OauthSecrets,
+    ) -> Result<Vec<YouTubeSubscription>, Box<dyn std::error::Error>> {
+        let mut subscriptions = Vec::new();
+        let mut page_token = String::new();
+
+        loop {
+            let response = Client::new()
+                .get(format!(
+                    "{}/subscriptions?part=snippet&mine=true&maxResults={}&pageToken={}",
+                    self.base_url,
+                    consts::PAGE_SIZE,
+                    page_token
+                ))
+                .bearer_auth(secrets.access_token.expose())
+                .header("Accept", "application/json")
+                .send()
+                .await?;
+
+            // Check if the response was successful
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await?;
+                return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
+            }
+
+            let json: Value = response.json().await?;
+            for item in json["items"].as_array().into_iter().flatten() {
+                let snippet = &item["snippet"];
+                let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+                subscriptions.push(YouTubeSubscription {
+                    subscription_id: text(&item["id"]),
+                    channel_id: text(&snippet["resourceId"]["channelId"]),
+                    channel_title: text(&snippet["title"]),
+                    description: text(&snippet["description"]),
+                    subscribed_at: text(&snippet["publishedAt"]),
+                });
+            }
+
+            // Handle pagination by checking for the nextPageToken
+            match json["nextPageToken"].as_str() {
+                Some(next_page_token) => page_token = next_page_token.to_string(),
+                None => break,
+            }
+        }
+
+        Ok(subscriptions)
+    }
+
+    async fn fetch_channel(
+        &self,
+        api_key: &Secret,
+        channel_url: &ChannelUrl,
+    ) -> Result<Option<YouTubeChannel>, Box<dyn std::error::Error>> {
+        let key = channel_url.canonical();
+        // The cache only saves quota; when it can't be read or written, the API is asked.
+        let cache = self.channel_cache.as_deref();
+        let cached_id = cache.and_then(|cache| cache.channel_id(&key).ok().flatten());
+
+        let channel = match cached_id {
+            Some(channel_id) => {
+                if let Some(channel) =
+                    cache.and_then(|cache| cache.channel(&channel_id).ok().flatten())
+                {
+                    return Ok(Some(channel));
+                }
+                self.lookup_channel(api_key, &ChannelUrl::Id(channel_id))
+                    .await?
+            }
+            None => self.lookup_channel(api_key, channel_url).await?,
+        };
+
+        if let (Some(cache), Some(channel)) = (cache, &channel) {
+            cache.save(&key, channel).ok();
+        }
+        Ok(channel)
+    }
+
+    async fn check_api_key(&self, api_key: &Secret) -> Result<(), Box<dyn std::error::Error>> {
+        // Listing a single channel by id costs one quota unit.
+        let response = Client::new()
+            .get(format!(
+                "{}/channels?part=id&id={}",
+                self.base_url, PROBE_CHANNEL_ID
+            ))
+            .header(API_KEY_HEADER, api_key.expose())
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(YouTubeApiError::from_response(status.as_u16(), body).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::channel_cache::ChannelCache;
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{header, method, path, query_param, query_param_is_missing},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn fetch_videos_pages_until_max_results() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("pageToken", ""))
+            .and(header(API_KEY_HEADER, "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": {"videoId": "a"}}, {"id": {"videoId": "b"}}],
+                "nextPageToken": "page-2"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("pageToken", "page-2"))
+            .and(query_param("maxResults", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": {"videoId": "c"}}],
+                "nextPageToken": "page-3"
+            })))
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+
+        // act
+        let videos = sut
+            .fetch_videos(&Secret::from("key"), "channel", 3)
+            .await
+            .unwrap();
+
+        // assert
+        let ids: Vec<_> = videos
+            .iter()
+            .map(|video| video["id"]["videoId"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn fetch_videos_surfaces_api_errors() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("quotaExceeded"))
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+
+        // act
+        let result = sut.fetch_videos(&Secret::from("key"), "channel", 3).await;
+
+        // assert
+        assert!(result.unwrap_err().to_string().contains("quotaExceeded"));
+    }
+
+    #[tokio::test]
+    async fn check_api_key_reports_a_disabled_api() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(header(API_KEY_HEADER, "good"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": []})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(header(API_KEY_HEADER, "disabled"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("accessNotConfigured"))
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+
+        // act
+        let good = sut.check_api_key(&Secret::from("good")).await;
+        let disabled = sut.check_api_key(&Secret::from("disabled")).await;
+
+        // assert
+        assert!(good.is_ok());
+        assert!(disabled
+            .unwrap_err()
+            .to_string()
+            .contains("accessNotConfigured"));
+    }
+
+    #[tokio::test]
+    async fn fetch_subscriptions_reads_every_page() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/subscriptions"))
+            .and(query_param("mine", "true"))
+            .and(query_param("pageToken", ""))
+            .and(header("authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{
+                    "id": "sub-a",
+                    "snippet": {
+                        "title": "A",
+                        "publishedAt": "2024-01-02T03:04:05Z",
+                        "resourceId": {"kind": "youtube#channel", "channelId": "UCa"}
+                    }
+                }],
+                "nextPageToken": "page-2"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/subscriptions"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "sub-b", "snippet": {"resourceId": {"channelId": "UCb"}}}]
+            })))
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+        let secrets = OauthSecrets {
+            access_token: "token".into(),
+            ..Default::default()
+        };
+
+        // act
+        let subscriptions = sut.fetch_subscriptions(&secrets).await.unwrap();
+
+        // assert
+        let ids: Vec<_> = subscriptions
+            .iter()
+            .map(|sub| (sub.subscription_id.as_str(), sub.channel_id.as_str()))
+            .collect();
+        assert_eq!(ids, vec![("sub-a", "UCa"), ("sub-b", "UCb")]);
+        assert_eq!(subscriptions[0].subscribed_at, "2024-01-02T03:04:05Z");
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_deletes_the_subscription_resource() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/subscriptions"))
+            .and(query_param("id", "sub-a"))
+            .and(header(API_KEY_HEADER, "key"))
+            .and(header("authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+        let secrets = OauthSecrets {
+            access_token: "token".into(),
+            ..Default::default()
+        };
+
+        // act
+        let deleted = sut
+            .unsubscribe(&Secret::from("key"), "sub-a", &secrets)
+            .await;
+        let missing = sut
+            .unsubscribe(&Secret::from("key"), "sub-b", &secrets)
+            .await;
+
+        // assert
+        assert!(deleted.is_ok());
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_channel_looks_up_handles_custom_names_and_videos() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("forHandle", "@handle"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"items": [{"id": "UChandle", "snippet": {"title": "Handle"}}]}),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("forHandle", "@legacy"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"pageInfo": {}})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("forUsername", "legacy"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"items": [{"id": "UClegacy"}]})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/videos"))
+            .and(query_param("id", "vid"))
+            .and(header(API_KEY_HEADER, "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"items": [{"id": "vid", "snippet": {"channelId": "UCvideo", "channelTitle": "Video"}}]}),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/videos"))
+            .and(query_param("id", "gone"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": []})))
+            .mount(&server)
+            .await;
+        // A `&` in a segment must stay part of the value instead of adding a filter.
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("forUsername", "foo&forHandle=@bar"))
+            .and(query_param_is_missing("forHandle"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(
+                    json!({"items": [{"id": "UCfoo", "snippet": {"title": "Foo"}}]}),
+                ),
+            )
+            .mount(&server)
+            .await;
+        let sut = YouTubeRepository::new(&server.uri());
+        let key = Secret::from("key");
+        let lookup = |url: &str| ChannelUrl::parse(url).unwrap();
+
+        // act
+        let handle = sut.fetch_channel(&key, &lookup("@handle")).await;
+        let custom = sut
+            .fetch_channel(&key, &lookup("youtube.com/c/legacy"))
+            .await;
+        let video = sut.fetch_channel(&key, &lookup("youtu.be/vid")).await;
+        let gone = sut.fetch_channel(&key, &lookup("youtu.be/gone")).await;
+        let ampersand = sut
+            .fetch_channel(&key, &lookup("youtube.com/user/foo&forHandle=@bar"))
+            .await;
+
+        // assert
+        let handle = handle.unwrap().unwrap();
+        assert_eq!(handle.channel_id, "UChandle");
+        assert_eq!(handle.channel_title, "Handle");
+        assert_eq!(custom.unwrap().unwrap().channel_id, "UClegacy");
+        let video = video.unwrap().unwrap();
+        assert_eq!(video.channel_id, "UCvideo");
+        assert_eq!(video.channel_title, "Video");
+        assert!(gone.unwrap().is_none());
+        assert_eq!(ampersand.unwrap().unwrap().channel_id, "UCfoo");
+    }
+
+    #[tokio::test]
+    async fn fetch_channel_reuses_cached_lookups() {
+        // arrange
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("forHandle", "@handle"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"items": [{"id": "UChandle", "snippet": {"title": "Handle"}}]}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/channels"))
+            .and(query_param("id", "UChandle"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"items": [{"id": "UChandle", "snippet": {"title": "Renamed"}}]}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("channels.json");
+        let day = Duration::from_secs(24 * 60 * 60);
+        let sut = YouTubeRepository::new(&server.uri()).with_channel_cache(ChannelCache::new(
+            path.clone(),
+            day,
+            day,
+        ));
+        // Shares the ids, but finds every title expired.
+        let stale_titles = YouTubeRepository::new(&server.uri())
+            .with_channel_cache(ChannelCache::new(path, day, Duration::ZERO));
+        let key = Secret::from("key");
+        let url = ChannelUrl::parse("@handle").unwrap();
+        let same_url = ChannelUrl::parse("https://www.youtube.com/@HANDLE").unwrap();
+
+        // act
+        let first = sut.fetch_channel(&key, &url).await.unwrap().unwrap();
+        let cached = sut.fetch_channel(&key, &same_url).await.unwrap().unwrap();
+        let refreshed = stale_titles
+            .fetch_channel(&key, &url)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // assert
+        assert_eq!(first.channel_title, "Handle");
+        assert_eq!(cached.channel_id, "UChandle");
+        assert_eq!(cached.channel_title, "Handle");
+        assert_eq!(refreshed.channel_title, "Renamed");
+        assert_eq!(sut.lookup_cost(), 1);
+        assert_eq!(stale_titles.lookup_cost(), 1);
+    }
 }
@@ -1,13 +1,23 @@
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 
-use super::interfaces::{
-    t_oauth2_service::TOAuth2Service, t_youtube_repository::TYouTubeRepository,
-    t_youtube_service::TYouTubeService,
+use super::{
+    errors::YouTubeApiError,
+    interfaces::{
+        t_oauth2_service::TOAuth2Service, t_youtube_repository::TYouTubeRepository,
+        t_youtube_service::TYouTubeService,
+    },
 };
 use crate::{
+    consts,
     models::{
+        channel_url::ChannelUrl,
         oath_2::OauthSecrets,
-        youtube::{FailedYoutubeSubscription, YouTubeChannel, YouTubeSubscriptionResult},
+        secret::Secret,
+        youtube::{
+            FailedYoutubeSubscription, YouTubeChannel, YouTubeSubscription,
+            YouTubeSubscriptionPlan, YouTubeSubscriptionResult, YouTubeSyncPlan,
+        },
     },
     tools::interfaces::t_csv_writer::TCSVWriter,
 };
@@ -31,7 +41,7 @@ where
 {
     async fn get_videos(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channel_id: &str,
         max_results: i32,
     ) -> anyhow::Result<Vec<Value>, Box<dyn std::error::Error>> {
@@ -53,31 +63,318 @@ where
 
     async fn subscribe(
         &self,
-        api_key: &str,
+        api_key: &Secret,
         channels: &[YouTubeChannel],
         secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>> {
+        // Reading the subscriptions once costs a unit per 50 of them, far less than the 50 units
+        // every duplicate `subscriptions.insert` would.
+        let plan = self.plan_subscriptions(channels, secrets).await?;
+        self.apply_subscriptions(api_key, plan, secrets).await
+    }
+
+    async fn apply_subscriptions(
+        &self,
+        api_key: &Secret,
+        plan: YouTubeSubscriptionPlan,
+        secrets: &mut OauthSecrets,
     ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>> {
         let mut result = YouTubeSubscriptionResult {
-            expected: channels.len() as i32,
+            expected: (plan.to_subscribe.len() + plan.invalid.len()) as i32,
+            already_subscribed: plan.already_subscribed,
             ..Default::default()
         };
 
-        for channel in channels {
-            match self.repository.subscribe(api_key, channel, secrets).await {
+        for channel in plan.invalid {
+            result.failed.push(FailedYoutubeSubscription {
+                channel_url: channel.channel_url,
+                channel_id: channel.channel_id,
+                error: "The row has no channel id".into(),
+            });
+        }
+
+        for channel in plan.to_subscribe {
+            let response = match self.repository.subscribe(api_key, &channel, secrets).await {
+                // The token most likely expired mid-run, refresh it and retry once.
+                Err(e) if YouTubeApiError::is_unauthorized(e.as_ref()) => {
+                    match self.refresh_access_token(secrets).await {
+                        Ok(_) => self.repository.subscribe(api_key, &channel, secrets).await,
+                        Err(refresh_error) => Err(refresh_error),
+                    }
+                }
+                other => other,
+            };
+
+            match response {
+                Ok(_) => {
+                    result.successful += 1;
+                }
+                // Subscribed to in the meantime, e.g. from another device.
+                Err(e) if YouTubeApiError::has_reason(e.as_ref(), "subscriptionDuplicate") => {
+                    result.expected -= 1;
+                    result.already_subscribed.push(channel);
+                }
+                Err(e) => {
+                    result.failed.push(FailedYoutubeSubscription {
+                        channel_url: channel.channel_url,
+                        channel_id: channel.channel_id,
+                        error: e,
+                    });
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    async fn unsubscribe(
+        &self,
+        api_key: &Secret,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>> {
+        let subscriptions = self.get_subscriptions(secrets).await?;
+
+        // Channels the plan would subscribe to are the ones the account isn't subscribed to.
+        let plan = plan(channels, &subscriptions);
+        let listed: HashSet<&str> = plan
+            .already_subscribed
+            .iter()
+            .map(|channel| channel.channel_id.as_str())
+            .collect();
+        let to_remove: Vec<_> = subscriptions
+            .iter()
+            .filter(|subscription| listed.contains(subscription.channel_id.as_str()))
+            .cloned()
+            .collect();
+
+        let mut result = self
+            .remove_subscriptions(api_key, &to_remove, secrets)
+            .await?;
+        result.not_subscribed.extend(plan.to_subscribe);
+        result.expected += plan.invalid.len() as i32;
+        for channel in plan.invalid {
+            result.failed.push(FailedYoutubeSubscription {
+                channel_url: channel.channel_url,
+                channel_id: channel.channel_id,
+                error: "The row has no channel id".into(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn remove_subscriptions(
+        &self,
+        api_key: &Secret,
+        subscriptions: &[YouTubeSubscription],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionResult, Box<dyn std::error::Error>> {
+        let mut result = YouTubeSubscriptionResult {
+            expected: subscriptions.len() as i32,
+            ..Default::default()
+        };
+
+        for subscription in subscriptions {
+            let subscription_id = subscription.subscription_id.as_str();
+            let response = match self
+                .repository
+                .unsubscribe(api_key, subscription_id, secrets)
+                .await
+            {
+                // The token most likely expired mid-run, refresh it and retry once.
+                Err(e) if YouTubeApiError::is_unauthorized(e.as_ref()) => {
+                    match self.refresh_access_token(secrets).await {
+                        Ok(_) => {
+                            self.repository
+                                .unsubscribe(api_key, subscription_id, secrets)
+                                .await
+                        }
+                        Err(refresh_error) => Err(refresh_error),
+                    }
+                }
+                other => other,
+            };
+
+            let channel = subscription.channel();
+            match response {
                 Ok(_) => {
                     result.successful += 1;
                 }
+                // Unsubscribed in the meantime, e.g. from another device.
+                Err(e) if YouTubeApiError::has_reason(e.as_ref(), "subscriptionNotFound") => {
+                    result.expected -= 1;
+                    result.not_subscribed.push(channel);
+                }
                 Err(e) => {
                     result.failed.push(FailedYoutubeSubscription {
-                        channel_url: channel.channel_url.clone(),
-                        channel_id: channel.channel_id.clone(),
+                        channel_url: channel.channel_url,
+                        channel_id: channel.channel_id,
                         error: e,
                     });
                 }
             }
         }
+
         Ok(result)
     }
+
+    async fn get_own_channel(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeChannel, Box<dyn std::error::Error>> {
+        match self.repository.fetch_own_channel(secrets).await {
+            Err(e) if YouTubeApiError::is_unauthorized(e.as_ref()) => {
+                self.refresh_access_token(secrets).await?;
+                self.repository.fetch_own_channel(secrets).await
+            }
+            other => other,
+        }
+    }
+
+    async fn get_subscriptions(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<Vec<YouTubeSubscription>, Box<dyn std::error::Error>> {
+        match self.repository.fetch_subscriptions(secrets).await {
+            Err(e) if YouTubeApiError::is_unauthorized(e.as_ref()) => {
+                self.refresh_access_token(secrets).await?;
+                self.repository.fetch_subscriptions(secrets).await
+            }
+            other => other,
+        }
+    }
+
+    async fn plan_subscriptions(
+        &self,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSubscriptionPlan, Box<dyn std::error::Error>> {
+        let subscriptions = self.get_subscriptions(secrets).await?;
+        Ok(plan(channels, &subscriptions))
+    }
+
+    async fn plan_sync(
+        &self,
+        channels: &[YouTubeChannel],
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<YouTubeSyncPlan, Box<dyn std::error::Error>> {
+        let subscriptions = self.get_subscriptions(secrets).await?;
+        let subscribe = plan(channels, &subscriptions);
+
+        let listed: HashSet<&str> = subscribe
+            .to_subscribe
+            .iter()
+            .chain(&subscribe.already_subscribed)
+            .map(|channel| channel.channel_id.as_str())
+            .collect();
+        let to_unsubscribe = subscriptions
+            .iter()
+            .filter(|subscription| !listed.contains(subscription.channel_id.as_str()))
+            .cloned()
+            .collect();
+
+        Ok(YouTubeSyncPlan {
+            subscribe,
+            to_unsubscribe,
+        })
+    }
+
+    async fn resolve_channels(
+        &self,
+        api_key: &Secret,
+        channels: Vec<YouTubeChannel>,
+    ) -> anyhow::Result<
+        (Vec<YouTubeChannel>, Vec<FailedYoutubeSubscription>),
+        Box<dyn std::error::Error>,
+    > {
+        let mut resolved = Vec::with_capacity(channels.len());
+        let mut failed = Vec::new();
+        // Lists often repeat a channel, look each url up once.
+        let mut lookups: HashMap<ChannelUrl, Option<YouTubeChannel>> = HashMap::new();
+
+        for channel in channels {
+            let mut channel = channel.normalized();
+            if !channel.channel_id.is_empty() || channel.channel_url.is_empty() {
+                resolved.push(channel);
+                continue;
+            }
+
+            let Some(channel_url) = ChannelUrl::parse(&channel.channel_url) else {
+                failed.push(FailedYoutubeSubscription {
+                    channel_url: channel.channel_url.clone(),
+                    channel_id: String::new(),
+                    error: "Not a YouTube channel or video url".into(),
+                });
+                continue;
+            };
+
+            let found = match lookups.get(&channel_url) {
+                Some(found) => found.clone(),
+                None => match self.repository.fetch_channel(api_key, &channel_url).await {
+                    Ok(found) => {
+                        lookups.insert(channel_url.clone(), found.clone());
+                        found
+                    }
+                    Err(e) => {
+                        failed.push(FailedYoutubeSubscription {
+                            channel_url: channel.channel_url.clone(),
+                            channel_id: String::new(),
+                            error: e,
+                        });
+                        continue;
+                    }
+                },
+            };
+
+            match found {
+                Some(found) => {
+                    channel.channel_id = found.channel_id;
+                    if channel.channel_title.is_empty() {
+                        channel.channel_title = found.channel_title;
+                    }
+                    resolved.push(channel);
+                }
+                None => failed.push(FailedYoutubeSubscription {
+                    channel_url: channel.channel_url.clone(),
+                    channel_id: String::new(),
+                    error: format!("No channel found for {channel_url}").into(),
+                }),
+            }
+        }
+
+        Ok((resolved, failed))
+    }
+}
+
+/// Normalizes and deduplicates `channels`, and sets apart the ones in `subscriptions`.
+fn plan(
+    channels: &[YouTubeChannel],
+    subscriptions: &[YouTubeSubscription],
+) -> YouTubeSubscriptionPlan {
+    let subscribed: HashSet<&str> = subscriptions
+        .iter()
+        .map(|subscription| subscription.channel_id.as_str())
+        .collect();
+
+    let mut plan = YouTubeSubscriptionPlan {
+        pages_read: subscriptions.len().div_ceil(consts::PAGE_SIZE).max(1) as u32,
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    for channel in channels {
+        let channel = channel.clone().normalized();
+        if channel.channel_id.is_empty() {
+            plan.invalid.push(channel);
+        } else if !seen.insert(channel.channel_id.clone()) {
+            plan.duplicates.push(channel);
+        } else if subscribed.contains(channel.channel_id.as_str()) {
+            plan.already_subscribed.push(channel);
+        } else {
+            plan.to_subscribe.push(channel);
+        }
+    }
+
+    plan
 }
 
 impl<'a, W, R, O> YouTubeService<'a, W, R, O>
@@ -93,24 +390,39 @@ where
             oauth2_service,
         }
     }
+
+    async fn refresh_access_token(
+        &self,
+        secrets: &mut OauthSecrets,
+    ) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+        self.oauth2_service
+            .refresh_access_token(secrets)
+            .await
+            .map(|_| ())
+            .map_err(|e| e as Box<dyn std::error::Error>)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{
-        borrow::BorrowMut,
-        cell::RefCell,
-        sync::{Arc, Mutex},
-    };
+    use std::sync::{Arc, Mutex};
 
-    use crate::{api::oauth2_service::OAuth2Service, tools::csv_writer::CSVWriter};
+    use crate::api::oauth2_service::OAuth2Service;
 
     use super::*;
     use serde_json::json;
-    use tokio::stream;
 
     pub struct MockRepo {
         videos: Vec<Value>,
+        /// When set, subscribing with any other access token is rejected as unauthorized.
+        valid_token: Option<String>,
+        subscribed: Mutex<Vec<String>>,
+        /// Ids of the subscription resources deleted.
+        unsubscribed: Mutex<Vec<String>>,
+        subscriptions: Vec<YouTubeSubscription>,
+        /// Times the subscriptions were listed.
+        listings: Mutex<i32>,
+        lookups: Mutex<i32>,
     }
     impl MockRepo {
         pub fn builder() -> MockRepoBuilder {
@@ -121,6 +433,8 @@ mod tests {
     #[derive(Default)]
     pub struct MockRepoBuilder {
         videos: Vec<Value>,
+        valid_token: Option<String>,
+        subscriptions: Vec<YouTubeSubscription>,
     }
 
     impl MockRepoBuilder {
@@ -129,12 +443,78 @@ mod tests {
             self
         }
 
+        pub fn with_valid_token(mut self, token: &str) -> MockRepoBuilder {
+            self.valid_token = Some(token.to_string());
+            self
+        }
+
+        /// The account is already subscribed to `channel_ids`.
+        pub fn with_subscriptions(mut self, channel_ids: &[&str]) -> MockRepoBuilder {
+            self.subscriptions = channel_ids
+                .iter()
+                .map(|channel_id| YouTubeSubscription {
+                    subscription_id: format!("sub-{channel_id}"),
+                    channel_id: channel_id.to_string(),
+                    ..Default::default()
+                })
+                .collect();
+            self
+        }
+
         pub fn build(self) -> MockRepo {
             MockRepo {
                 videos: self.videos,
+                valid_token: self.valid_token,
+                subscribed: Mutex::new(Vec::new()),
+                unsubscribed: Mutex::new(Vec::new()),
+                subscriptions: self.subscriptions,
+                listings: Mutex::new(0),
+                lookups: Mutex::new(0),
             }
         }
     }
+
+    /// Hands out `token` whenever asked to refresh, counting the refreshes.
+    #[derive(Default)]
+    pub struct MockOAuth2Service {
+        token: String,
+        refreshes: Mutex<i32>,
+    }
+
+    impl TOAuth2Service for MockOAuth2Service {
+        async fn request_access_token(
+            &self,
+            secrets: &mut OauthSecrets,
+            _scopes: &[&str],
+        ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>> {
+            secrets.access_token = self.token.as_str().into();
+            Ok(secrets.clone())
+        }
+
+        async fn cached_access_token(
+            &self,
+            secrets: &mut OauthSecrets,
+        ) -> anyhow::Result<Option<OauthSecrets>, Box<dyn std::error::Error + Send>> {
+            secrets.access_token = self.token.as_str().into();
+            Ok(Some(secrets.clone()))
+        }
+
+        async fn refresh_access_token(
+            &self,
+            secrets: &mut OauthSecrets,
+        ) -> anyhow::Result<OauthSecrets, Box<dyn std::error::Error + Send>> {
+            *self.refreshes.lock().unwrap() += 1;
+            secrets.access_token = self.token.as_str().into();
+            Ok(secrets.clone())
+        }
+
+        async fn revoke_token(
+            &self,
+            _secrets: &OauthSecrets,
+        ) -> anyhow::Result<bool, Box<dyn std::error::Error + Send>> {
+            Ok(true)
+        }
+    }
     #[derive(Default)]
     pub struct MockWriter {
         // Need interior mutability here to represent records written to a file.
@@ -161,6 +541,7 @@ mod tests {
     }
 
     impl MockWriterBuilder {
+        #[allow(dead_code)]
         pub fn with_records(mut self, records: Vec<Value>) -> MockWriterBuilder {
             self.records = records;
             self
@@ -176,8 +557,8 @@ mod tests {
         fn write_records(
             &self,
             records: Vec<Value>,
-            path: &str,
-            headers: &[String],
+            _path: &str,
+            _headers: &[String],
         ) -> anyhow::Result<(), Box<dyn std::error::Error>> {
             for record in records {
                 self.records.lock().unwrap().push(record);
@@ -190,20 +571,86 @@ mod tests {
     impl TYouTubeRepository for MockRepo {
         async fn fetch_videos(
             &self,
-            api_key: &str,
-            channel_id: &str,
-            max_results: i32,
+            _api_key: &Secret,
+            _channel_id: &str,
+            _max_results: i32,
         ) -> anyhow::Result<Vec<Value>, Box<dyn std::error::Error>> {
             Ok(self.videos.clone())
         }
 
         async fn subscribe(
             &self,
-            api_key: &str,
+            _api_key: &Secret,
             channel: &YouTubeChannel,
             secrets: &mut OauthSecrets,
         ) -> Result<(), Box<dyn std::error::Error>> {
-            todo!()
+            if let Some(valid_token) = &self.valid_token {
+                if secrets.access_token.expose() != valid_token {
+                    return Err(YouTubeApiError::from_response(401, String::new()).into());
+                }
+            }
+
+            self.subscribed
+                .lock()
+                .unwrap()
+                .push(channel.channel_id.clone());
+            Ok(())
+        }
+
+        async fn unsubscribe(
+            &self,
+            _api_key: &Secret,
+            subscription_id: &str,
+            secrets: &OauthSecrets,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            if let Some(valid_token) = &self.valid_token {
+                if secrets.access_token.expose() != valid_token {
+                    return Err(YouTubeApiError::from_response(401, String::new()).into());
+                }
+            }
+
+            self.unsubscribed
+                .lock()
+                .unwrap()
+                .push(subscription_id.to_string());
+            Ok(())
+        }
+
+        async fn fetch_own_channel(
+            &self,
+            _secrets: &OauthSecrets,
+        ) -> Result<YouTubeChannel, Box<dyn std::error::Error>> {
+            Ok(YouTubeChannel::default())
+        }
+
+        async fn fetch_subscriptions(
+            &self,
+            _secrets: &OauthSecrets,
+        ) -> Result<Vec<YouTubeSubscription>, Box<dyn std::error::Error>> {
+            *self.listings.lock().unwrap() += 1;
+            Ok(self.subscriptions.clone())
+        }
+
+        /// Resolves handles to `UC<handle>`, titled after the handle, and knows no other
+        /// channels.
+        async fn fetch_channel(
+            &self,
+            _api_key: &Secret,
+            channel_url: &ChannelUrl,
+        ) -> Result<Option<YouTubeChannel>, Box<dyn std::error::Error>> {
+            *self.lookups.lock().unwrap() += 1;
+            Ok(match channel_url {
+                ChannelUrl::Handle(handle) => Some(YouTubeChannel {
+                    channel_url: String::new(),
+                    channel_id: format!("UC{handle}"),
+                    channel_title: handle.clone(),
+                }),
+                _ => None,
+            })
+        }
+
+        async fn check_api_key(&self, _api_key: &Secret) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
         }
     }
 
@@ -215,16 +662,16 @@ mod tests {
         let writer = MockWriter::builder().build();
         let oauth2_service = OAuth2Service::default();
         let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
-        let api_key = "key";
+        let api_key = Secret::from("key");
         let channel_id = "channel_id";
         let max_results = 1;
 
         // act
-        let result = sut.get_videos(api_key, channel_id, max_results).await;
+        let result = sut.get_videos(&api_key, channel_id, max_results).await;
 
         // Assert
         assert!(result.is_ok());
-        assert!(result.unwrap().len() == 1 as usize);
+        assert!(result.unwrap().len() == 1_usize);
     }
 
     #[tokio::test]
@@ -247,6 +694,284 @@ mod tests {
             "{} records written!",
             writer.records().lock().unwrap().len()
         );
-        assert!(writer.records().lock().unwrap().len() == 1 as usize);
+        assert!(writer.records().lock().unwrap().len() == 1_usize);
+    }
+
+    #[tokio::test]
+    async fn subscribe_refreshes_expired_token_and_retries() {
+        // arrange
+        let repo = MockRepo::builder().with_valid_token("fresh").build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service {
+            token: "fresh".to_string(),
+            ..Default::default()
+        };
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels = vec![
+            YouTubeChannel {
+                channel_id: "a".to_string(),
+                ..Default::default()
+            },
+            YouTubeChannel {
+                channel_id: "b".to_string(),
+                ..Default::default()
+            },
+        ];
+        let mut secrets = OauthSecrets {
+            access_token: "expired".into(),
+            ..Default::default()
+        };
+
+        // act
+        let result = sut
+            .subscribe(&Secret::from("key"), &channels, &mut secrets)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(result.successful, 2);
+        assert!(result.failed.is_empty());
+        assert_eq!(secrets.access_token.expose(), "fresh");
+        assert_eq!(*oauth2_service.refreshes.lock().unwrap(), 1);
+        assert_eq!(*repo.subscribed.lock().unwrap(), vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn subscribe_retries_only_once() {
+        // arrange
+        let repo = MockRepo::builder().with_valid_token("fresh").build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service {
+            token: "still-wrong".to_string(),
+            ..Default::default()
+        };
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels = vec![YouTubeChannel {
+            channel_id: "a".to_string(),
+            ..Default::default()
+        }];
+        let mut secrets = OauthSecrets::default();
+
+        // act
+        let result = sut
+            .subscribe(&Secret::from("key"), &channels, &mut secrets)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(result.successful, 0);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(*oauth2_service.refreshes.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn plan_subscriptions_sets_apart_duplicates_and_existing_subscriptions() {
+        // arrange
+        let repo = MockRepo::builder().with_subscriptions(&["UCold"]).build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service::default();
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channel = |url: &str, id: &str| YouTubeChannel {
+            channel_url: url.to_string(),
+            channel_id: id.to_string(),
+            ..Default::default()
+        };
+        let channels = vec![
+            channel("", " UCnew "),
+            channel("https://www.youtube.com/channel/UCnew", ""),
+            channel("https://www.youtube.com/channel/UCold?view=0", ""),
+            channel("https://www.youtube.com/@handle", ""),
+        ];
+        let mut secrets = OauthSecrets::default();
+
+        // act
+        let plan = sut
+            .plan_subscriptions(&channels, &mut secrets)
+            .await
+            .unwrap();
+
+        // assert
+        let ids = |channels: &[YouTubeChannel]| -> Vec<String> {
+            channels.iter().map(|c| c.channel_id.clone()).collect()
+        };
+        assert_eq!(ids(&plan.to_subscribe), vec!["UCnew"]);
+        assert_eq!(ids(&plan.duplicates), vec!["UCnew"]);
+        assert_eq!(ids(&plan.already_subscribed), vec!["UCold"]);
+        assert_eq!(plan.invalid.len(), 1);
+        assert_eq!(plan.quota_cost(), 51);
+        assert!(repo.subscribed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn subscribe_skips_channels_already_subscribed_to() {
+        // arrange
+        let repo = MockRepo::builder().with_subscriptions(&["old"]).build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service::default();
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels: Vec<_> = ["old", "new", "new"]
+            .into_iter()
+            .map(|channel_id| YouTubeChannel {
+                channel_id: channel_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let mut secrets = OauthSecrets::default();
+
+        // act
+        let result = sut
+            .subscribe(&Secret::from("key"), &channels, &mut secrets)
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(result.expected, 1);
+        assert_eq!(result.successful, 1);
+        assert_eq!(result.already_subscribed.len(), 1);
+        assert_eq!(result.already_subscribed[0].channel_id, "old");
+        assert_eq!(*repo.subscribed.lock().unwrap(), vec!["new"]);
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_deletes_only_existing_subscriptions() {
+        // arrange
+        let repo = MockRepo::builder()
+            .with_subscriptions(&["a", "b"])
+            .with_valid_token("fresh")
+            .build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service {
+            token: "fresh".to_string(),
+            ..Default::default()
+        };
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels: Vec<_> = ["b", "c"]
+            .into_iter()
+            .map(|channel_id| YouTubeChannel {
+                channel_id: channel_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let mut secrets = OauthSecrets {
+            access_token: "expired".into(),
+            ..Default::default()
+        };
+
+        // act
+        let result = sut
+            .unsubscribe(&Secret::from("key"), &channels, &mut secrets)
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(result.expected, 1);
+        assert_eq!(result.successful, 1);
+        assert_eq!(result.not_subscribed.len(), 1);
+        assert_eq!(result.not_subscribed[0].channel_id, "c");
+        assert_eq!(*repo.unsubscribed.lock().unwrap(), vec!["sub-b"]);
+        assert_eq!(*oauth2_service.refreshes.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn plan_sync_removes_subscriptions_the_file_does_not_list() {
+        // arrange
+        let repo = MockRepo::builder().with_subscriptions(&["a", "b"]).build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service::default();
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels: Vec<_> = ["b", "c"]
+            .into_iter()
+            .map(|channel_id| YouTubeChannel {
+                channel_id: channel_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let mut secrets = OauthSecrets::default();
+
+        // act
+        let plan = sut.plan_sync(&channels, &mut secrets).await.unwrap();
+
+        // assert
+        assert_eq!(plan.subscribe.to_subscribe[0].channel_id, "c");
+        assert_eq!(plan.subscribe.already_subscribed[0].channel_id, "b");
+        assert_eq!(plan.to_unsubscribe.len(), 1);
+        assert_eq!(plan.to_unsubscribe[0].channel_id, "a");
+        assert_eq!(plan.quota_cost(), 101);
+    }
+
+    #[tokio::test]
+    async fn applying_a_sync_plan_does_not_list_the_subscriptions_again() {
+        // arrange
+        let repo = MockRepo::builder().with_subscriptions(&["a", "b"]).build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service::default();
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels: Vec<_> = ["b", "c"]
+            .into_iter()
+            .map(|channel_id| YouTubeChannel {
+                channel_id: channel_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let mut secrets = OauthSecrets::default();
+        let key = Secret::from("key");
+        let plan = sut.plan_sync(&channels, &mut secrets).await.unwrap();
+
+        // act
+        let added = sut
+            .apply_subscriptions(&key, plan.subscribe, &mut secrets)
+            .await
+            .unwrap();
+        let removed = sut
+            .remove_subscriptions(&key, &plan.to_unsubscribe, &mut secrets)
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(added.successful, 1);
+        assert_eq!(removed.successful, 1);
+        assert_eq!(*repo.subscribed.lock().unwrap(), vec!["c"]);
+        assert_eq!(*repo.unsubscribed.lock().unwrap(), vec!["sub-a"]);
+        assert_eq!(*repo.listings.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_channels_looks_up_each_url_once() {
+        // arrange
+        let repo = MockRepo::builder().build();
+        let writer = MockWriter::builder().build();
+        let oauth2_service = MockOAuth2Service::default();
+        let sut = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let channels: Vec<_> = [
+            "https://www.youtube.com/@handle",
+            "youtube.com/@handle",
+            "https://www.youtube.com/channel/UCdirect",
+            "https://youtu.be/unknown",
+            "https://example.com",
+        ]
+        .into_iter()
+        .map(|url| YouTubeChannel {
+            channel_url: url.to_string(),
+            ..Default::default()
+        })
+        .collect();
+
+        // act
+        let (resolved, failed) = sut
+            .resolve_channels(&Secret::from("key"), channels)
+            .await
+            .unwrap();
+
+        // assert
+        let ids: Vec<_> = resolved.iter().map(|c| c.channel_id.as_str()).collect();
+        assert_eq!(ids, vec!["UChandle", "UChandle", "UCdirect"]);
+        assert_eq!(resolved[0].channel_title, "handle");
+        let failed_urls: Vec<_> = failed.iter().map(|f| f.channel_url.as_str()).collect();
+        assert_eq!(
+            failed_urls,
+            vec!["https://youtu.be/unknown", "https://example.com"]
+        );
+        assert!(failed[0].error.to_string().contains("video unknown"));
+        assert_eq!(*repo.lookups.lock().unwrap(), 2);
     }
 }
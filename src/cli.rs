@@ -0,0 +1,241 @@
+use crate::consts;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use std::{fmt::Display, path::PathBuf};
+
+/// Manages the YouTube subscriptions of one or more accounts.
+#[derive(Parser)]
+#[command(version, about, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Flags every command accepts.
+#[derive(Args)]
+pub struct GlobalArgs {
+    /// Profile (account) to run as.
+    #[arg(long, short, global = true, default_value = consts::DEFAULT_ACCOUNT)]
+    pub profile: String,
+
+    /// TOML config file to read, instead of `youtube-auto-sub.toml` in the working directory.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// How to authorize: loopback, manual or device. Overrides OAUTH_FLOW.
+    #[arg(long, global = true, value_name = "FLOW")]
+    pub auth_flow: Option<String>,
+
+    /// Where to cache tokens: file, encrypted or keyring. Overrides TOKEN_STORE.
+    #[arg(long, global = true, value_name = "BACKEND")]
+    pub token_store: Option<String>,
+
+    /// Format of the results printed on stdout.
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Print more detail; may be repeated.
+    #[arg(long, short, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Only print results and errors.
+    #[arg(long, short, global = true)]
+    pub quiet: bool,
+}
+
+impl GlobalArgs {
+    /// Prints a progress message on stderr, unless `--quiet` was given. Results go to stdout,
+    /// so they can be piped regardless.
+    pub fn info(&self, message: impl Display) {
+        if !self.quiet {
+            eprintln!("{message}");
+        }
+    }
+
+    /// Prints a message on stderr when `--verbose` was given.
+    pub fn detail(&self, message: impl Display) {
+        if self.verbose > 0 {
+            eprintln!("{message}");
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Subscribe to every channel in a CSV file.
+    Subscribe {
+        /// CSV file of channels, with channel_url, channel_id and channel_title columns.
+        file: PathBuf,
+        /// Only read the account and print what would be subscribed to, and the quota it costs.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Unsubscribe from every channel in a CSV file.
+    Unsubscribe {
+        /// CSV file of channels, with channel_url, channel_id and channel_title columns.
+        file: PathBuf,
+    },
+    /// Export the account's subscriptions as CSV (or JSON with `-o json`), in the shape
+    /// `subscribe` reads.
+    ExportSubscriptions {
+        /// File to write to instead of stdout.
+        #[arg(long, short)]
+        file: Option<PathBuf>,
+    },
+    /// Export the latest videos of one or more channels to a CSV file.
+    FetchVideos(FetchVideosArgs),
+    /// Show which subscriptions a CSV file of channels would add or remove.
+    Plan {
+        /// CSV file of the channels the account should be subscribed to, with channel_url,
+        /// channel_id and channel_title columns.
+        file: PathBuf,
+        /// Never remove subscriptions the file doesn't list.
+        #[arg(long)]
+        add_only: bool,
+    },
+    /// Add and remove subscriptions until the account matches a CSV file of channels.
+    Apply {
+        /// CSV file of the channels the account should be subscribed to, with channel_url,
+        /// channel_id and channel_title columns.
+        file: PathBuf,
+        /// Never remove subscriptions the file doesn't list.
+        #[arg(long)]
+        add_only: bool,
+        /// Apply without showing the plan and asking first.
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Subscribe one profile to every channel another profile is subscribed to.
+    Migrate {
+        /// Profile to copy the subscriptions of; defaults to --profile.
+        #[arg(long, value_name = "PROFILE")]
+        from: Option<String>,
+        /// Profile to subscribe.
+        #[arg(long, value_name = "PROFILE")]
+        to: String,
+        /// Afterwards, unsubscribe the source from every channel the target now has.
+        #[arg(long)]
+        unsubscribe_source: bool,
+        /// Unsubscribe the source without listing the channels and asking first.
+        #[arg(long, short, requires = "unsubscribe_source")]
+        yes: bool,
+    },
+    /// Compare the subscriptions of two profiles or exported files; fails when they differ.
+    Diff {
+        /// An exported CSV file, or else a profile name.
+        a: String,
+        /// An exported CSV file, or else a profile name.
+        b: String,
+    },
+    /// Authorize the profile, so later commands don't have to ask.
+    Auth {
+        /// Only ask for read access; commands that change subscriptions will ask again.
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Revoke the profile's access at Google and delete its cached tokens.
+    Logout,
+    /// Check the configuration, OAuth client, token cache and API access, without authorizing.
+    Doctor,
+    /// Manage the account profiles.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Inspect or clear the cache of channel ids and titles looked up from urls.
+    #[command(subcommand)]
+    Cache(CacheCommand),
+}
+
+#[derive(Args)]
+pub struct FetchVideosArgs {
+    /// Channel to fetch the videos of.
+    #[arg(required_unless_present = "channels", conflicts_with = "channels")]
+    pub channel_id: Option<String>,
+
+    /// CSV file of channels to fetch the videos of, with channel_url, channel_id and
+    /// channel_title columns.
+    #[arg(long, value_name = "FILE")]
+    pub channels: Option<PathBuf>,
+
+    /// CSV file to write the videos to.
+    #[arg(long, short, default_value = "videos.csv")]
+    pub file: PathBuf,
+
+    /// Number of videos to fetch per channel, newest first.
+    #[arg(long, default_value_t = consts::MAX_RESULTS)]
+    pub max_results: i32,
+
+    /// Comma separated header row, naming the video id, title, description and publish date
+    /// columns.
+    #[arg(long, value_delimiter = ',')]
+    pub headers: Option<Vec<String>>,
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// List the profiles and the channel each one is bound to.
+    List,
+    /// Add a profile and authorize it.
+    Add {
+        name: String,
+        /// Client secret file to use for this profile unless CLIENT_SECRET_FILE is set.
+        #[arg(long)]
+        client_secret_file: Option<PathBuf>,
+    },
+    /// Remove a profile and its cached tokens.
+    Remove { name: String },
+    /// Show a profile's channel and token status; defaults to --profile.
+    Show { name: Option<String> },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// List the cached channel ids and titles, and which of them expired.
+    Show,
+    /// Delete the cached entries, so every url is looked up again.
+    Clear {
+        /// Only delete the entries that expired.
+        #[arg(long)]
+        expired: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn global_flags_are_accepted_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "youtube-auto-sub",
+            "subscribe",
+            "a.csv",
+            "-p",
+            "work",
+            "-o",
+            "json",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.global.profile, "work");
+        assert!(cli.global.output == OutputFormat::Json);
+        assert!(matches!(cli.command, Command::Subscribe { .. }));
+        assert!(
+            Cli::try_parse_from(["youtube-auto-sub", "subscribe", "a.csv", "-v", "-q"]).is_err()
+        );
+    }
+}
@@ -0,0 +1,419 @@
+pub(crate) mod auth;
+pub(crate) mod cache;
+pub(crate) mod diff;
+pub(crate) mod doctor;
+pub(crate) mod export_subscriptions;
+pub(crate) mod fetch_videos;
+pub(crate) mod logout;
+pub(crate) mod migrate;
+pub(crate) mod profile;
+pub(crate) mod subscribe;
+pub(crate) mod sync;
+pub(crate) mod unsubscribe;
+
+use crate::{
+    api::{
+        interfaces::{t_oauth2_service::TOAuth2Service, t_youtube_service::TYouTubeService},
+        oauth2_service::OAuth2Service,
+        youtube_repo::YouTubeRepository,
+    },
+    cli::GlobalArgs,
+    consts,
+    models::{
+        config::{days_to_duration, Config, ConfigError, Settings},
+        oath_2::{AuthFlow, OauthSecrets, TokenBackend},
+        profile::Profile,
+        secret::Secret,
+        youtube::YouTubeChannel,
+    },
+    tools::{
+        channel_cache::ChannelCache,
+        csv_reader::CSVReader,
+        encrypted_token_store::EncryptedTokenStore,
+        file_token_store::FileTokenStore,
+        interfaces::{
+            t_csv_reader::TCSVReader, t_profile_store::TProfileStore, t_token_store::TTokenStore,
+        },
+        keyring_token_store::KeyringTokenStore,
+        paths,
+    },
+};
+use anyhow::{anyhow, Context};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// The OAuth client settings an authorization needs, in the order they are reported. The
+/// redirect uri comes last, as the device flow does without it.
+const OAUTH_CLIENT_REQUIRED: [&str; 5] = [
+    consts::CLIENT_ID,
+    consts::CLIENT_SECRET,
+    consts::AUTH_URI,
+    consts::TOKEN_URI,
+    consts::REDIRECT_URI,
+];
+
+/// The OAuth client settings the configured flow needs.
+fn oauth_client_required(settings: &Settings) -> &'static [&'static str] {
+    match settings.auth_flow.unwrap_or_default() {
+        AuthFlow::Loopback | AuthFlow::Manual => &OAUTH_CLIENT_REQUIRED,
+        AuthFlow::Device => &OAUTH_CLIENT_REQUIRED[..4],
+    }
+}
+
+/// Looks up the profile named `name`. The default profile exists without being added.
+pub fn resolve_profile(profiles: &impl TProfileStore, name: &str) -> anyhow::Result<Profile> {
+    match profiles.get(name)? {
+        Some(profile) => Ok(profile),
+        None if name == consts::DEFAULT_ACCOUNT => Ok(Profile::new(name)),
+        None => Err(anyhow!(
+            "Unknown profile `{name}`, add it with `profile add {name}`"
+        )),
+    }
+}
+
+/// Reads the settings from the per-user config file, the project config file (or `--config`),
+/// the environment and the command line, each overriding the ones before.
+pub fn load_settings(args: &GlobalArgs) -> anyhow::Result<Settings> {
+    let mut config = Config::default();
+
+    let user_file = paths::config_dir().join(consts::CONFIG_FILE);
+    let project_file = match &args.config {
+        Some(path) => path.clone(),
+        None => PathBuf::from(consts::PROJECT_CONFIG_FILE),
+    };
+    for (path, required) in [(user_file, false), (project_file, args.config.is_some())] {
+        match fs::read_to_string(&path) {
+            Ok(toml) => config.merge_toml(&toml, &path.display().to_string()),
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => {}
+            Err(e) => {
+                return Err(anyhow!(
+                    "Could not read config file {}: {e}",
+                    path.display()
+                ))
+            }
+        }
+    }
+
+    config.merge_env(|name| env::var(name).ok());
+    if let Some(flow) = &args.auth_flow {
+        config.set(consts::OAUTH_FLOW, flow, "--auth-flow");
+    }
+    if let Some(token_store) = &args.token_store {
+        config.set(consts::TOKEN_STORE, token_store, "--token-store");
+    }
+
+    let mut settings = config.resolve()?;
+    settings.verbose = args.verbose > 0;
+    Ok(settings)
+}
+
+/// Settings a command can't run without.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    /// `youtube_api_key`.
+    ApiKey,
+    /// A client secret file, or the OAuth client settings it would provide.
+    OAuthClient,
+}
+
+/// Checks `settings` provide everything in `requirements`, reporting every missing setting at
+/// once. `profile` may bring its own client secret file.
+pub fn require(
+    settings: &Settings,
+    profile: &Profile,
+    requirements: &[Requirement],
+) -> anyhow::Result<()> {
+    let mut missing = Vec::new();
+
+    if requirements.contains(&Requirement::ApiKey) && settings.youtube_api_key.is_none() {
+        missing.push(consts::YOUTUBE_API_KEY);
+    }
+    let has_client_secret_file =
+        settings.client_secret_file.is_some() || profile.client_secret_file.is_some();
+    if requirements.contains(&Requirement::OAuthClient) && !has_client_secret_file {
+        missing.extend(
+            oauth_client_required(settings)
+                .iter()
+                .copied()
+                .filter(|name| settings.oauth_client(name).is_none()),
+        );
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(ConfigError(
+        missing
+            .into_iter()
+            .map(|name| {
+                format!(
+                    "`{}` is not set, in a config file or as env {name}",
+                    name.to_ascii_lowercase()
+                )
+            })
+            .collect(),
+    )
+    .into())
+}
+
+/// The API key, or an error naming where it can be set.
+pub fn api_key(settings: &Settings) -> anyhow::Result<&Secret> {
+    settings.youtube_api_key.as_ref().ok_or_else(|| {
+        anyhow!("`youtube_api_key` is not set, in a config file or as env YOUTUBE_API_KEY")
+    })
+}
+
+/// The store the OAuth tokens of every profile are cached in, picked by `token_store`.
+pub fn token_store(settings: &Settings) -> anyhow::Result<Box<dyn TTokenStore + Send + Sync>> {
+    Ok(match settings.token_store {
+        TokenBackend::File => Box::new(FileTokenStore::default()),
+        TokenBackend::Encrypted => {
+            let passphrase = settings.token_store_passphrase.as_ref().ok_or_else(|| {
+                anyhow!("TOKEN_STORE_PASSPHRASE must be set to use the encrypted token store!")
+            })?;
+            Box::new(EncryptedTokenStore::with_passphrase(
+                passphrase.expose().to_string(),
+            ))
+        }
+        TokenBackend::Keyring => match &settings.secret_tool {
+            Some(program) => Box::new(KeyringTokenStore::new(program.clone())),
+            None => Box::new(KeyringTokenStore::default()),
+        },
+    })
+}
+
+/// The cache of channel lookups, expiring entries after the configured TTLs.
+pub fn channel_cache(settings: &Settings) -> ChannelCache {
+    ChannelCache::new(
+        paths::cache_dir().join(consts::CHANNEL_CACHE_FILE),
+        settings
+            .channel_id_ttl
+            .unwrap_or_else(|| days_to_duration(consts::DEFAULT_CHANNEL_ID_TTL_DAYS)),
+        settings
+            .channel_metadata_ttl
+            .unwrap_or_else(|| days_to_duration(consts::DEFAULT_CHANNEL_METADATA_TTL_DAYS)),
+    )
+}
+
+/// The YouTube API, looking channels up in the channel cache first.
+pub fn youtube_repo(settings: &Settings) -> YouTubeRepository {
+    YouTubeRepository::default().with_channel_cache(channel_cache(settings))
+}
+
+/// Reads the OAuth client config from the configured client secret file, or else the profile's
+/// client secret file, with the individual settings taking precedence.
+pub fn load_oauth_secrets(profile: &Profile, settings: &Settings) -> anyhow::Result<OauthSecrets> {
+    let client_secret_file = settings
+        .client_secret_file
+        .clone()
+        .or_else(|| profile.client_secret_file.clone());
+
+    let mut secrets = match client_secret_file {
+        Some(path) => {
+            let json = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read client secret file {path}"))?;
+            OauthSecrets::from_client_secret_json(&json)
+                .with_context(|| format!("Invalid client secret file {path}"))?
+        }
+        None => OauthSecrets::default(),
+    };
+    secrets.apply_overrides(|name| settings.oauth_client(name));
+
+    if secrets.device_auth_url.is_empty() {
+        secrets.device_auth_url = consts::DEFAULT_DEVICE_AUTH_URI.to_string();
+    }
+    if secrets.revoke_url.is_empty() {
+        secrets.revoke_url = consts::DEFAULT_REVOKE_URI.to_string();
+    }
+
+    let values = [
+        secrets.client_id.as_str(),
+        secrets.client_secret.expose(),
+        secrets.auth_url.as_str(),
+        secrets.token_url.as_str(),
+        secrets.redirect_url.as_str(),
+    ];
+    let missing: Vec<_> = oauth_client_required(settings)
+        .iter()
+        .copied()
+        .zip(values)
+        .filter(|(_, value)| value.is_empty())
+        .map(|(name, _)| name)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "{} must be set, or provided through {}!",
+            missing.join(", "),
+            consts::CLIENT_SECRET_FILE
+        ));
+    }
+
+    Ok(secrets)
+}
+
+/// Builds the OAuth service caching tokens for `profile`, configured from `settings`.
+pub fn oauth2_service(profile: &Profile, settings: &Settings) -> anyhow::Result<OAuth2Service> {
+    let mut oauth2_builder = OAuth2Service::builder()
+        .with_account(&profile.name)
+        .with_store(token_store(settings)?)
+        .with_verbose(settings.verbose);
+    if let Some(timeout) = settings.redirect_timeout {
+        oauth2_builder = oauth2_builder.with_redirect_timeout(timeout);
+    }
+    if let Some(ephemeral_port) = settings.ephemeral_port {
+        oauth2_builder = oauth2_builder.with_ephemeral_port(ephemeral_port);
+    }
+    if let Some(flow) = settings.auth_flow {
+        oauth2_builder = oauth2_builder.with_flow(flow);
+    }
+
+    Ok(oauth2_builder.build())
+}
+
+/// Gets an access token for `profile` that was granted `scopes`, authorizing when needed.
+pub async fn access_token(
+    oauth2_service: &OAuth2Service,
+    profile: &Profile,
+    settings: &Settings,
+    scopes: &[&str],
+) -> anyhow::Result<OauthSecrets> {
+    let mut secrets = load_oauth_secrets(profile, settings)?;
+    match oauth2_service
+        .request_access_token(&mut secrets, scopes)
+        .await
+    {
+        Ok(secrets) => Ok(secrets),
+        Err(e) => Err(anyhow!(
+            "Could not authorize profile `{}`: {e}",
+            profile.name
+        )),
+    }
+}
+
+/// Reads the channels listed in the CSV file `file`.
+pub fn read_channels(
+    file: &Path,
+) -> anyhow::Result<Vec<YouTubeChannel>, Box<dyn std::error::Error>> {
+    let file_handle =
+        File::open(file).with_context(|| format!("Could not open {}", file.display()))?;
+    CSVReader::read_records(&file_handle)
+}
+
+/// Fills in the ids of the channels only listed by url, warning about and leaving out the ones
+/// that can't be resolved, which are counted in the returned number. The API key is only needed
+/// when there is something to look up.
+pub async fn resolve_channels<S>(
+    api: &S,
+    channels: Vec<YouTubeChannel>,
+    settings: &Settings,
+    args: &GlobalArgs,
+) -> anyhow::Result<(Vec<YouTubeChannel>, usize), Box<dyn std::error::Error>>
+where
+    S: TYouTubeService,
+{
+    let channels: Vec<_> = channels
+        .into_iter()
+        .map(YouTubeChannel::normalized)
+        .collect();
+    let unresolved = channels
+        .iter()
+        .filter(|channel| channel.channel_id.is_empty() && !channel.channel_url.is_empty())
+        .count();
+    if unresolved == 0 {
+        return Ok((channels, 0));
+    }
+
+    args.info(format!("Resolving {unresolved} channel urls"));
+    let (channels, failed) = api.resolve_channels(api_key(settings)?, channels).await?;
+    for failed in &failed {
+        args.info(format!(
+            "Could not resolve {}: {}",
+            failed.channel_url, failed.error
+        ));
+    }
+
+    Ok((channels, failed.len()))
+}
+
+/// Asks `question` on stderr, and reads a yes or no from stdin. Anything but yes is a no.
+pub fn confirm(question: &str) -> anyhow::Result<bool> {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Records which channel `profile` is bound to, if that is not known yet.
+pub async fn bind_channel<S, P>(
+    api: &S,
+    secrets: &mut OauthSecrets,
+    profile: &mut Profile,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    S: TYouTubeService,
+    P: TProfileStore,
+{
+    if profile.channel_id.is_some() {
+        return Ok(());
+    }
+
+    let channel = api.get_own_channel(secrets).await?;
+    profile.channel_id = Some(channel.channel_id);
+    profile.channel_title = Some(channel.channel_title);
+    profiles.save(profile)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_redirecting_flows_need_a_redirect_uri() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client_secret.json");
+        // A "TVs and limited input devices" client lists no redirect uris.
+        fs::write(
+            &path,
+            r#"{"installed": {
+                "client_id": "id",
+                "client_secret": "secret",
+                "auth_uri": "https://accounts.google.com/o/oauth2/auth",
+                "token_uri": "https://oauth2.googleapis.com/token"
+            }}"#,
+        )
+        .unwrap();
+        let settings = |flow| {
+            let mut config = Config::default();
+            config.set(
+                consts::CLIENT_SECRET_FILE,
+                &path.display().to_string(),
+                "test",
+            );
+            config.set(consts::OAUTH_FLOW, flow, "test");
+            config.resolve().unwrap()
+        };
+        let profile = Profile::default();
+
+        // act
+        let device = load_oauth_secrets(&profile, &settings("device"));
+        let loopback = load_oauth_secrets(&profile, &settings("loopback"));
+
+        // assert
+        assert_eq!(device.unwrap().client_id, "id");
+        assert!(loopback
+            .unwrap_err()
+            .to_string()
+            .starts_with(consts::REDIRECT_URI));
+    }
+}
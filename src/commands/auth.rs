@@ -0,0 +1,46 @@
+use super::{
+    access_token, bind_channel, oauth2_service, require, resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::youtube_service::YouTubeService,
+    cli::GlobalArgs,
+    consts,
+    models::config::Settings,
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+
+/// Authorizes the profile up front, with read-only or full access.
+pub async fn run<P>(
+    read_only: bool,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let scopes = if read_only {
+        [consts::YOUTUBE_READONLY_SCOPE]
+    } else {
+        [consts::YOUTUBE_SCOPE]
+    };
+
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    require(settings, &profile, &[Requirement::OAuthClient])?;
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, &scopes).await?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+
+    args.detail(secrets.token_summary());
+    println!(
+        "Profile `{}` is authorized, bound to {}",
+        profile.name,
+        profile.binding()
+    );
+
+    Ok(())
+}
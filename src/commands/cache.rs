@@ -0,0 +1,109 @@
+use super::channel_cache;
+use crate::{
+    cli::{CacheCommand, GlobalArgs, OutputFormat},
+    models::{channel_cache::ChannelCacheEntry, config::Settings, oath_2::unix_now},
+    tools::interfaces::t_channel_cache::TChannelCache,
+};
+use serde_json::json;
+use std::io;
+
+pub fn run(
+    command: CacheCommand,
+    args: &GlobalArgs,
+    settings: &Settings,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let cache = channel_cache(settings);
+
+    match command {
+        CacheCommand::Show => {
+            let entries = cache.entries()?;
+            let expired = entries.iter().filter(|entry| entry.expired).count();
+            args.info(format!(
+                "{} entries in {}, {expired} expired",
+                entries.len(),
+                cache.path().display()
+            ));
+            print_entries(&entries, args.output)?;
+        }
+        CacheCommand::Clear { expired } => {
+            let removed = cache.clear(expired)?;
+            let which = if expired { "expired " } else { "" };
+            println!(
+                "Removed {removed} {which}entries from {}",
+                cache.path().display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_entries(
+    entries: &[ChannelCacheEntry],
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Text => {
+            let width = entries
+                .iter()
+                .map(|entry| entry.key.len())
+                .max()
+                .unwrap_or_default();
+            let now = unix_now();
+            for entry in entries {
+                println!(
+                    "{:<8}  {:<width$}  {}  ({}{})",
+                    entry.kind,
+                    entry.key,
+                    entry.value,
+                    age(now.saturating_sub(entry.cached_at)),
+                    if entry.expired { ", expired" } else { "" }
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let json: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "kind": entry.kind.to_string(),
+                        "key": entry.key,
+                        "value": entry.value,
+                        "cached_at": entry.cached_at,
+                        "expired": entry.expired,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["kind", "key", "value", "cached_at", "expired"])?;
+            for entry in entries {
+                writer.write_record([
+                    entry.kind.to_string(),
+                    entry.key.clone(),
+                    entry.value.clone(),
+                    entry.cached_at.to_string(),
+                    entry.expired.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `secs` as a rough age, e.g. `3 days old`.
+fn age(secs: u64) -> String {
+    let (count, unit) = match secs {
+        0..=59 => (secs, "second"),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+
+    format!("{count} {unit}{plural} old")
+}
@@ -0,0 +1,255 @@
+use super::{
+    access_token, bind_channel, oauth2_service, read_channels, require, resolve_channels,
+    resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::{
+        interfaces::t_youtube_service::TYouTubeService, oauth2_service::OAuth2Service,
+        youtube_service::YouTubeService,
+    },
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{config::Settings, youtube::YouTubeChannel},
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{collections::HashSet, io, path::Path};
+
+/// Comparing only reads the accounts.
+const SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+
+/// Channels of two sets, split by which sets they are in.
+#[derive(Default)]
+struct ChannelDiff {
+    only_a: Vec<YouTubeChannel>,
+    only_b: Vec<YouTubeChannel>,
+    both: Vec<YouTubeChannel>,
+}
+
+/// Compares the subscriptions of `a` and `b`, each either an exported CSV file or a profile name,
+/// and fails when they differ.
+pub async fn run<P>(
+    a: &str,
+    b: &str,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let channels_a = load(a, args, settings, profiles).await?;
+    let channels_b = load(b, args, settings, profiles).await?;
+    let diff = diff(&channels_a, &channels_b);
+
+    print_diff(&diff, a, b, args.output)?;
+
+    if !diff.only_a.is_empty() || !diff.only_b.is_empty() {
+        return Err(anyhow!("The subscriptions of {a} and {b} differ").into());
+    }
+
+    Ok(())
+}
+
+/// Reads the channels of an existing file, or else the subscriptions of the profile `source`.
+/// A `source` that looks like a path but doesn't exist is reported as a missing file.
+async fn load<P>(
+    source: &str,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<Vec<YouTubeChannel>, Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let path = Path::new(source);
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+
+    if path.is_file() {
+        let channels = read_channels(path)?;
+        args.detail(format!("Read {} channels from {source}", channels.len()));
+
+        // Resolving urls only needs the API key.
+        let oauth2_service = OAuth2Service::default();
+        let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+        let (channels, _) = resolve_channels(&api, channels, settings, args).await?;
+        return Ok(channels);
+    }
+    if looks_like_path(source) {
+        return Err(anyhow!("File {source} does not exist").into());
+    }
+
+    let mut profile = resolve_profile(profiles, source)?;
+    require(settings, &profile, &[Requirement::OAuthClient])?;
+
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, SCOPES).await?;
+
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+
+    let subscriptions = api.get_subscriptions(&mut secrets).await?;
+    args.detail(format!(
+        "Read {} subscriptions of profile `{}`",
+        subscriptions.len(),
+        profile.name
+    ));
+    Ok(subscriptions
+        .iter()
+        .map(|subscription| subscription.channel())
+        .collect())
+}
+
+/// Whether `source` was meant as a file rather than a profile name.
+fn looks_like_path(source: &str) -> bool {
+    source.contains('/')
+        || source.contains(std::path::MAIN_SEPARATOR)
+        || source.to_ascii_lowercase().ends_with(".csv")
+}
+
+/// Splits the channels of `a` and `b` by channel id, keeping the order they are listed in.
+/// Rows without a channel id can't be compared and are left out.
+fn diff(a: &[YouTubeChannel], b: &[YouTubeChannel]) -> ChannelDiff {
+    let ids = |channels: &[YouTubeChannel]| -> HashSet<String> {
+        channels
+            .iter()
+            .map(|channel| channel.channel_id.clone())
+            .collect()
+    };
+    let (ids_a, ids_b) = (ids(a), ids(b));
+
+    let mut diff = ChannelDiff::default();
+    let mut seen = HashSet::new();
+    for channel in a {
+        if channel.channel_id.is_empty() || !seen.insert(&channel.channel_id) {
+            continue;
+        }
+        if ids_b.contains(&channel.channel_id) {
+            diff.both.push(channel.clone());
+        } else {
+            diff.only_a.push(channel.clone());
+        }
+    }
+    for channel in b {
+        if !channel.channel_id.is_empty()
+            && !ids_a.contains(&channel.channel_id)
+            && seen.insert(&channel.channel_id)
+        {
+            diff.only_b.push(channel.clone());
+        }
+    }
+
+    diff
+}
+
+fn print_diff(
+    diff: &ChannelDiff,
+    a: &str,
+    b: &str,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let sides = [
+        ("only-a", &diff.only_a),
+        ("only-b", &diff.only_b),
+        ("both", &diff.both),
+    ];
+
+    match output {
+        OutputFormat::Text => {
+            for channel in &diff.only_a {
+                println!("< {} {}", channel.channel_id, channel.channel_title);
+            }
+            for channel in &diff.only_b {
+                println!("> {} {}", channel.channel_id, channel.channel_title);
+            }
+            println!(
+                "{} only in {a}, {} only in {b}, {} in both.",
+                diff.only_a.len(),
+                diff.only_b.len(),
+                diff.both.len()
+            );
+        }
+        OutputFormat::Json => {
+            let channels = |channels: &[YouTubeChannel]| -> Vec<_> {
+                channels
+                    .iter()
+                    .map(|channel| {
+                        json!({
+                            "channel_id": channel.channel_id,
+                            "channel_url": channel.channel_url,
+                            "channel_title": channel.channel_title,
+                        })
+                    })
+                    .collect()
+            };
+            let json = json!({
+                "a": a,
+                "b": b,
+                "only_a": channels(&diff.only_a),
+                "only_b": channels(&diff.only_b),
+                "both": channels(&diff.both),
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel, in either set.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["side", "channel_url", "channel_id", "channel_title"])?;
+            for (side, channels) in sides {
+                for channel in channels {
+                    writer.write_record([
+                        side,
+                        &channel.channel_url,
+                        &channel.channel_id,
+                        &channel.channel_title,
+                    ])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_like_path_tells_files_from_profile_names() {
+        assert!(looks_like_path("exports/work.csv"));
+        assert!(looks_like_path("./work"));
+        assert!(looks_like_path("work.CSV"));
+        assert!(!looks_like_path("work"));
+        assert!(!looks_like_path("me@example.com"));
+    }
+
+    #[test]
+    fn diff_splits_channels_by_set() {
+        // arrange
+        let channels = |ids: &[&str]| -> Vec<YouTubeChannel> {
+            ids.iter()
+                .map(|channel_id| YouTubeChannel {
+                    channel_id: channel_id.to_string(),
+                    ..Default::default()
+                })
+                .collect()
+        };
+        let a = channels(&["x", "shared", "x", ""]);
+        let b = channels(&["shared", "y", "y"]);
+
+        // act
+        let diff = diff(&a, &b);
+
+        // assert
+        let ids = |channels: &[YouTubeChannel]| -> Vec<String> {
+            channels.iter().map(|c| c.channel_id.clone()).collect()
+        };
+        assert_eq!(ids(&diff.only_a), vec!["x"]);
+        assert_eq!(ids(&diff.only_b), vec!["y"]);
+        assert_eq!(ids(&diff.both), vec!["shared"]);
+    }
+}
@@ -0,0 +1,368 @@
+use super::{
+    load_oauth_secrets, load_settings, oauth2_service, resolve_profile, token_store, youtube_repo,
+};
+use crate::{
+    api::{
+        interfaces::{t_oauth2_service::TOAuth2Service, t_youtube_repository::TYouTubeRepository},
+        oauth2_service::OAuth2Service,
+    },
+    cli::{GlobalArgs, OutputFormat},
+    tools::interfaces::t_profile_store::TProfileStore,
+};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{fmt, io, net::TcpListener};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Pass,
+    /// Works, but will ask the user for something.
+    Warn,
+    Fail,
+    /// Not run, because an earlier check failed or there is nothing to check.
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Pass => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+/// Collects the outcome of each check, in the order they ran.
+#[derive(Default)]
+struct Checklist(Vec<Check>);
+
+impl Checklist {
+    fn add(&mut self, name: &'static str, status: Status, detail: impl fmt::Display) {
+        self.0.push(Check {
+            name,
+            status,
+            detail: detail.to_string(),
+        });
+    }
+
+    /// Records `result` as passed with its value or failed with its error, and hands the value
+    /// on to the checks depending on it.
+    fn check<T>(
+        &mut self,
+        name: &'static str,
+        result: anyhow::Result<T>,
+        detail: impl FnOnce(&T) -> String,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.add(name, Status::Pass, detail(&value));
+                Some(value)
+            }
+            Err(e) => {
+                self.add(name, Status::Fail, format!("{e:#}"));
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, name: &'static str) {
+        self.add(name, Status::Skip, "an earlier check failed");
+    }
+
+    fn failed(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .count()
+    }
+}
+
+/// Checks the setup end to end, from the configuration to an authorized API request, and prints
+/// a checklist. Nothing here asks for consent, so it is safe to run anywhere.
+pub async fn run<P>(
+    args: &GlobalArgs,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut checks = Checklist::default();
+
+    let settings = checks.check("Configuration", load_settings(args), |_| {
+        "every setting is valid".to_string()
+    });
+    let profile = checks.check(
+        "Profile",
+        resolve_profile(profiles, &args.profile),
+        |profile| format!("`{}`, bound to {}", profile.name, profile.binding()),
+    );
+
+    let Some(settings) = settings else {
+        for name in [
+            "API key",
+            "OAuth client",
+            "OAuth urls",
+            "Redirect port",
+            "Token cache",
+            "Authorized request",
+        ] {
+            checks.skip(name);
+        }
+        return print_checks(&checks, args.output);
+    };
+    let repo = youtube_repo(&settings);
+
+    match &settings.youtube_api_key {
+        Some(api_key) => {
+            let result = repo
+                .check_api_key(api_key)
+                .await
+                .map_err(|e| anyhow!("{e}"));
+            checks.check("API key", result, |_| {
+                "accepted by the YouTube Data API".to_string()
+            });
+        }
+        None => checks.add(
+            "API key",
+            Status::Fail,
+            "`youtube_api_key` is not set, in a config file or as env YOUTUBE_API_KEY",
+        ),
+    }
+
+    let Some(profile) = profile else {
+        for name in [
+            "OAuth client",
+            "OAuth urls",
+            "Redirect port",
+            "Token cache",
+            "Authorized request",
+        ] {
+            checks.skip(name);
+        }
+        return print_checks(&checks, args.output);
+    };
+
+    let secrets = checks.check(
+        "OAuth client",
+        load_oauth_secrets(&profile, &settings),
+        |secrets| format!("client id {}", secrets.client_id),
+    );
+    // Building the service opens the token store, so a failure here is the token cache's.
+    let oauth2_service = oauth2_service(&profile, &settings);
+
+    match &secrets {
+        Some(secrets) => {
+            checks.check("OAuth urls", OAuth2Service::validate_urls(secrets), |_| {
+                "every url parses".to_string()
+            });
+            match oauth2_service
+                .as_ref()
+                .map(|service| service.loopback_addr(secrets))
+            {
+                Ok(Ok(Some(addr))) => {
+                    let result = TcpListener::bind(addr).map_err(|e| {
+                        anyhow!(
+                            "cannot listen on {addr} ({e}); free it, or set \
+                             oauth_ephemeral_port = true"
+                        )
+                    });
+                    checks.check("Redirect port", result, |_| format!("{addr} is free"));
+                }
+                Ok(Ok(None)) => checks.add(
+                    "Redirect port",
+                    Status::Pass,
+                    "nothing to listen on, or the OS picks a free port",
+                ),
+                Ok(Err(e)) => checks.add("Redirect port", Status::Fail, format!("{e:#}")),
+                Err(_) => checks.skip("Redirect port"),
+            }
+        }
+        None => {
+            checks.skip("OAuth urls");
+            checks.skip("Redirect port");
+        }
+    }
+
+    let oauth2_service = match oauth2_service {
+        Ok(oauth2_service) => oauth2_service,
+        Err(e) => {
+            checks.add("Token cache", Status::Fail, format!("{e:#}"));
+            checks.skip("Authorized request");
+            return print_checks(&checks, args.output);
+        }
+    };
+
+    let cached = match token_store(&settings).and_then(|store| store.load(&profile.name)) {
+        Ok(Some(token)) => {
+            let refresh = if token.refresh_token.is_some() {
+                "with"
+            } else {
+                "without"
+            };
+            checks.add(
+                "Token cache",
+                Status::Pass,
+                format!(
+                    "cached {refresh} a refresh token, scopes: {}",
+                    token.scopes.join(" ")
+                ),
+            );
+            true
+        }
+        Ok(None) => {
+            checks.add(
+                "Token cache",
+                Status::Warn,
+                format!(
+                    "no token cached, run `auth -p {}` to authorize",
+                    profile.name
+                ),
+            );
+            false
+        }
+        Err(e) => {
+            checks.add("Token cache", Status::Fail, format!("{e:#}"));
+            false
+        }
+    };
+
+    match secrets {
+        Some(mut secrets) if cached => {
+            match oauth2_service.cached_access_token(&mut secrets).await {
+                Ok(Some(secrets)) => {
+                    let result = repo
+                        .fetch_own_channel(&secrets)
+                        .await
+                        .map_err(|e| anyhow!("{e}"));
+                    checks.check("Authorized request", result, |channel| {
+                        format!(
+                            "authorized as {} ({})",
+                            channel.channel_title, channel.channel_id
+                        )
+                    });
+                }
+                Ok(None) => checks.add(
+                    "Authorized request",
+                    Status::Fail,
+                    format!(
+                        "the cached token was revoked, run `auth -p {}` again",
+                        profile.name
+                    ),
+                ),
+                Err(e) => checks.add("Authorized request", Status::Fail, e),
+            }
+        }
+        Some(_) => checks.add("Authorized request", Status::Skip, "no token cached"),
+        None => checks.skip("Authorized request"),
+    }
+
+    print_checks(&checks, args.output)
+}
+
+/// Prints the checklist, and fails when any check did.
+fn print_checks(
+    checks: &Checklist,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Text => {
+            for check in &checks.0 {
+                println!(
+                    "[{:<4}] {}: {}",
+                    check.status,
+                    check.name,
+                    check.detail.replace('\n', "\n         ")
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let json: Vec<_> = checks
+                .0
+                .iter()
+                .map(|check| {
+                    json!({
+                        "check": check.name,
+                        "status": check.status.to_string().to_lowercase(),
+                        "detail": check.detail,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["check", "status", "detail"])?;
+            for check in &checks.0 {
+                writer.write_record([
+                    check.name,
+                    &check.status.to_string().to_lowercase(),
+                    &check.detail,
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    let failed = checks.failed();
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} checks failed", checks.0.len()).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_hands_on_the_value_or_records_the_error() {
+        // arrange
+        let mut sut = Checklist::default();
+
+        // act
+        let passed = sut.check("First", Ok(1), |value| format!("value {value}"));
+        let failed = sut.check(
+            "Second",
+            Err::<i32, _>(anyhow!("broken")),
+            |_| unreachable!(),
+        );
+        sut.skip("Third");
+
+        // assert
+        assert_eq!(passed, Some(1));
+        assert_eq!(failed, None);
+        let statuses: Vec<_> = sut.0.iter().map(|check| check.status).collect();
+        assert_eq!(statuses, [Status::Pass, Status::Fail, Status::Skip]);
+        assert_eq!(sut.0[0].detail, "value 1");
+        assert_eq!(sut.0[1].detail, "broken");
+    }
+
+    #[test]
+    fn print_checks_fails_only_when_a_check_failed() {
+        // arrange
+        let mut passing = Checklist::default();
+        passing.add("First", Status::Pass, "");
+        passing.add("Second", Status::Warn, "");
+        passing.skip("Third");
+        let mut failing = Checklist::default();
+        failing.add("First", Status::Pass, "");
+        failing.add("Second", Status::Fail, "");
+
+        // act
+        let passed = print_checks(&passing, OutputFormat::Json);
+        let failed = print_checks(&failing, OutputFormat::Json);
+
+        // assert
+        assert!(passed.is_ok());
+        assert_eq!(failed.unwrap_err().to_string(), "1 of 2 checks failed");
+    }
+}
@@ -0,0 +1,147 @@
+use super::{
+    access_token, bind_channel, oauth2_service, require, resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::{interfaces::t_youtube_service::TYouTubeService, youtube_service::YouTubeService},
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{config::Settings, youtube::YouTubeSubscription},
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use anyhow::Context;
+use serde::Serialize;
+use std::{fs::File, io, path::Path};
+
+/// Exporting only reads the account.
+const SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+
+/// A row of the export. The first three columns are the ones `subscribe` reads, so an export
+/// can be fed straight back in.
+#[derive(Serialize)]
+struct ExportedChannel<'a> {
+    channel_url: String,
+    channel_id: &'a str,
+    channel_title: &'a str,
+    subscribed_at: &'a str,
+    description: &'a str,
+}
+
+impl<'a> From<&'a YouTubeSubscription> for ExportedChannel<'a> {
+    fn from(subscription: &'a YouTubeSubscription) -> Self {
+        Self {
+            channel_url: subscription.channel().channel_url,
+            channel_id: &subscription.channel_id,
+            channel_title: &subscription.channel_title,
+            subscribed_at: &subscription.subscribed_at,
+            description: &subscription.description,
+        }
+    }
+}
+
+/// Writes every subscription of the profile to `file`, or to stdout.
+pub async fn run<P>(
+    file: Option<&Path>,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    require(settings, &profile, &[Requirement::OAuthClient])?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, SCOPES).await?;
+
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+
+    let subscriptions = api.get_subscriptions(&mut secrets).await?;
+
+    match file {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Could not create {}", path.display()))?;
+            write_subscriptions(&subscriptions, args.output, file)?;
+            args.info(format!(
+                "Exported {} subscriptions of profile `{}` to {}",
+                subscriptions.len(),
+                profile.name,
+                path.display()
+            ));
+        }
+        None => write_subscriptions(&subscriptions, args.output, io::stdout())?,
+    }
+
+    Ok(())
+}
+
+/// Writes `subscriptions` as a JSON array with `--output json`, and as CSV otherwise.
+fn write_subscriptions(
+    subscriptions: &[YouTubeSubscription],
+    output: OutputFormat,
+    out: impl io::Write,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let rows = subscriptions.iter().map(ExportedChannel::from);
+
+    match output {
+        OutputFormat::Json => {
+            let rows: Vec<_> = rows.collect();
+            serde_json::to_writer_pretty(out, &rows)?;
+        }
+        OutputFormat::Text | OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::youtube::YouTubeChannel,
+        tools::{csv_reader::CSVReader, interfaces::t_csv_reader::TCSVReader},
+    };
+
+    #[test]
+    fn csv_export_reads_back_as_channels() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subscriptions.csv");
+        let subscriptions = vec![YouTubeSubscription {
+            subscription_id: "sub-a".to_string(),
+            channel_id: "UCa".to_string(),
+            channel_title: "A, with a comma".to_string(),
+            description: "Line one\nline two".to_string(),
+            subscribed_at: "2024-01-02T03:04:05Z".to_string(),
+        }];
+
+        // act
+        write_subscriptions(
+            &subscriptions,
+            OutputFormat::Csv,
+            File::create(&path).unwrap(),
+        )
+        .unwrap();
+        let channels: Vec<YouTubeChannel> =
+            CSVReader::read_records(&File::open(&path).unwrap()).unwrap();
+
+        // assert
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].channel_id, "UCa");
+        assert_eq!(channels[0].channel_title, "A, with a comma");
+        assert_eq!(
+            channels[0].channel_url,
+            "https://www.youtube.com/channel/UCa"
+        );
+    }
+}
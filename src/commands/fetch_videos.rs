@@ -0,0 +1,80 @@
+use super::{api_key, read_channels, resolve_channels, youtube_repo};
+use crate::{
+    api::{
+        interfaces::t_youtube_service::TYouTubeService, oauth2_service::OAuth2Service,
+        youtube_service::YouTubeService,
+    },
+    cli::{FetchVideosArgs, GlobalArgs},
+    models::config::Settings,
+    tools::csv_writer::CSVWriter,
+};
+use anyhow::anyhow;
+
+/// Header row used when `--headers` is not given, in the order `CSVWriter` writes the columns.
+const HEADERS: [&str; 4] = ["Video ID", "Title", "Description", "Published At"];
+
+/// Writes the latest videos of the requested channels to a single CSV file. Searching public
+/// videos only needs the API key, so no profile has to be authorized.
+pub async fn run(
+    fetch_args: &FetchVideosArgs,
+    args: &GlobalArgs,
+    settings: &Settings,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let headers = match &fetch_args.headers {
+        Some(headers) if headers.len() != HEADERS.len() => {
+            return Err(anyhow!(
+                "--headers needs {} names (video id, title, description, publish date), got {}",
+                HEADERS.len(),
+                headers.len()
+            )
+            .into());
+        }
+        Some(headers) => headers.clone(),
+        None => HEADERS.iter().map(|header| header.to_string()).collect(),
+    };
+
+    let api_key = api_key(settings)?;
+
+    // Instantiate the dependnecies.
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = OAuth2Service::default();
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+
+    let channel_ids: Vec<_> = match (&fetch_args.channel_id, &fetch_args.channels) {
+        (Some(channel_id), _) => vec![channel_id.clone()],
+        (None, Some(path)) => {
+            let channels = read_channels(path)?;
+            let (channels, _) = resolve_channels(&api, channels, settings, args).await?;
+            channels
+                .into_iter()
+                .map(|channel| channel.channel_id)
+                .filter(|channel_id| !channel_id.is_empty())
+                .collect()
+        }
+        (None, None) => unreachable!("clap requires a channel id or --channels"),
+    };
+
+    let mut videos = Vec::new();
+    for channel_id in &channel_ids {
+        let channel_videos = api
+            .get_videos(api_key, channel_id, fetch_args.max_results)
+            .await
+            .map_err(|e| anyhow!("Could not fetch the videos of channel {channel_id}: {e}"))?;
+        args.detail(format!(
+            "Fetched {} videos of channel {channel_id}",
+            channel_videos.len()
+        ));
+        videos.extend(channel_videos);
+    }
+
+    let count = videos.len();
+    let path = fetch_args.file.display().to_string();
+    api.write_to_csv(videos, &path, &headers)?;
+    args.info(format!(
+        "Wrote {count} videos of {} channels to {path}",
+        channel_ids.len()
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use super::{load_oauth_secrets, oauth2_service, resolve_profile};
+use crate::{
+    api::interfaces::t_oauth2_service::TOAuth2Service, models::config::Settings,
+    tools::interfaces::t_profile_store::TProfileStore,
+};
+use anyhow::anyhow;
+
+/// Revokes the profile's tokens at Google and deletes them locally.
+pub async fn run<P>(
+    name: &str,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, name)?;
+    let secrets = load_oauth_secrets(&profile, settings)?;
+    let oauth2_service = oauth2_service(&profile, settings)?;
+
+    match oauth2_service.revoke_token(&secrets).await {
+        Ok(true) => println!(
+            "Revoked access and deleted the cached tokens of profile `{}`.",
+            profile.name
+        ),
+        Ok(false) => println!(
+            "Profile `{}` has no cached tokens, nothing to revoke.",
+            profile.name
+        ),
+        Err(e) => return Err(anyhow!("Could not log out profile `{}`: {e}", profile.name).into()),
+    }
+
+    // The next authorization may well be for a different Google account.
+    if profile.channel_id.is_some() {
+        profile.channel_id = None;
+        profile.channel_title = None;
+        profiles.save(&profile)?;
+    }
+
+    Ok(())
+}
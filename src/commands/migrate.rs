@@ -0,0 +1,346 @@
+use super::{
+    access_token, api_key, bind_channel, confirm, oauth2_service, require, resolve_profile,
+    youtube_repo, Requirement,
+};
+use crate::{
+    api::{interfaces::t_youtube_service::TYouTubeService, youtube_service::YouTubeService},
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{
+        config::Settings,
+        youtube::{YouTubeChannel, YouTubeSubscriptionResult},
+    },
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+/// Subscribing the target, or unsubscribing the source, changes the account.
+const SCOPES: &[&str] = &[consts::YOUTUBE_SCOPE];
+/// A source that is only read from needs no more than read access.
+const SOURCE_SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+
+/// What happened to one of the source's subscriptions.
+struct MigratedChannel {
+    channel: YouTubeChannel,
+    /// `subscribed`, `already-subscribed` or `failed`.
+    target: &'static str,
+    /// `kept`, `unsubscribed` or `failed`.
+    source: &'static str,
+    error: Option<String>,
+}
+
+/// Subscribes the `to` profile to every channel the `from` profile is subscribed to, then
+/// unsubscribes `from` from the channels that made it across if `unsubscribe_source` is set.
+/// Unsubscribing is listed and confirmed first, unless `yes` is given.
+pub async fn run<P>(
+    from: &str,
+    to: &str,
+    unsubscribe_source: bool,
+    yes: bool,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    if from == to {
+        return Err(anyhow!("Can't migrate profile `{from}` to itself").into());
+    }
+
+    let mut source = resolve_profile(profiles, from)?;
+    let mut target = resolve_profile(profiles, to)?;
+    require(
+        settings,
+        &source,
+        &[Requirement::ApiKey, Requirement::OAuthClient],
+    )?;
+    require(settings, &target, &[Requirement::OAuthClient])?;
+    let api_key = api_key(settings)?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let source_scopes = if unsubscribe_source {
+        SCOPES
+    } else {
+        SOURCE_SCOPES
+    };
+
+    // Each profile has its own cached tokens, and so its own OAuth service.
+    let source_oauth2_service = oauth2_service(&source, settings)?;
+    let mut source_secrets =
+        access_token(&source_oauth2_service, &source, settings, source_scopes).await?;
+    let source_api = YouTubeService::new(&writer, &repo, &source_oauth2_service);
+    bind_channel(&source_api, &mut source_secrets, &mut source, profiles).await?;
+
+    let target_oauth2_service = oauth2_service(&target, settings)?;
+    let mut target_secrets =
+        access_token(&target_oauth2_service, &target, settings, SCOPES).await?;
+    let target_api = YouTubeService::new(&writer, &repo, &target_oauth2_service);
+    bind_channel(&target_api, &mut target_secrets, &mut target, profiles).await?;
+
+    if source.channel_id.is_some() && source.channel_id == target.channel_id {
+        return Err(anyhow!(
+            "Profiles `{from}` and `{to}` are bound to the same channel {}",
+            source.binding()
+        )
+        .into());
+    }
+
+    let subscriptions = source_api.get_subscriptions(&mut source_secrets).await?;
+    let channels: Vec<_> = subscriptions
+        .iter()
+        .map(|subscription| subscription.channel())
+        .collect();
+
+    // Ask before anything changes; channels that fail to move are kept regardless.
+    if unsubscribe_source && !yes && !channels.is_empty() {
+        for channel in &channels {
+            eprintln!("- {} {}", channel.channel_id, channel.channel_title);
+        }
+        let question = format!(
+            "Subscribe `{}` to these {} channels, then unsubscribe `{}` from them?",
+            target.name,
+            channels.len(),
+            source.name
+        );
+        if !confirm(&question)? {
+            return Err(anyhow!("Aborted, nothing was changed").into());
+        }
+    }
+    args.info(format!(
+        "Migrating {} subscriptions from `{}` ({}) to `{}` ({})",
+        channels.len(),
+        source.name,
+        source.binding(),
+        target.name,
+        target.binding()
+    ));
+
+    let subscribed = target_api
+        .subscribe(api_key, &channels, &mut target_secrets)
+        .await?;
+    let mut migrated = migrated_channels(&channels, &subscribed);
+
+    if unsubscribe_source {
+        let moved: HashSet<&str> = migrated
+            .iter()
+            .filter(|migrated| migrated.target != "failed")
+            .map(|migrated| migrated.channel.channel_id.as_str())
+            .collect();
+        let to_remove: Vec<_> = subscriptions
+            .iter()
+            .filter(|subscription| moved.contains(subscription.channel_id.as_str()))
+            .cloned()
+            .collect();
+        args.info(format!(
+            "Unsubscribing `{}` from {} channels",
+            source.name,
+            to_remove.len()
+        ));
+        let unsubscribed = source_api
+            .remove_subscriptions(api_key, &to_remove, &mut source_secrets)
+            .await?;
+        apply_unsubscribed(&mut migrated, &unsubscribed);
+    }
+
+    print_report(&migrated, args.output)
+}
+
+/// Pairs every channel with how subscribing the target to it went.
+fn migrated_channels(
+    channels: &[YouTubeChannel],
+    subscribed: &YouTubeSubscriptionResult,
+) -> Vec<MigratedChannel> {
+    let errors: HashMap<&str, String> = subscribed
+        .failed
+        .iter()
+        .map(|failed| (failed.channel_id.as_str(), failed.error.to_string()))
+        .collect();
+
+    channels
+        .iter()
+        .map(|channel| {
+            let id = channel.channel_id.as_str();
+            let error = errors.get(id).cloned();
+            let target = if error.is_some() {
+                "failed"
+            } else if subscribed
+                .already_subscribed
+                .iter()
+                .any(|already| already.channel_id == id)
+            {
+                "already-subscribed"
+            } else {
+                "subscribed"
+            };
+
+            MigratedChannel {
+                channel: channel.clone(),
+                target,
+                source: "kept",
+                error,
+            }
+        })
+        .collect()
+}
+
+/// Records how unsubscribing the source from the moved channels went.
+fn apply_unsubscribed(migrated: &mut [MigratedChannel], unsubscribed: &YouTubeSubscriptionResult) {
+    let errors: HashMap<&str, String> = unsubscribed
+        .failed
+        .iter()
+        .map(|failed| (failed.channel_id.as_str(), failed.error.to_string()))
+        .collect();
+
+    for migrated in migrated.iter_mut().filter(|m| m.target != "failed") {
+        match errors.get(migrated.channel.channel_id.as_str()) {
+            Some(error) => {
+                migrated.source = "failed";
+                migrated.error = Some(error.clone());
+            }
+            None => migrated.source = "unsubscribed",
+        }
+    }
+}
+
+fn print_report(
+    migrated: &[MigratedChannel],
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Text => {
+            for failed in migrated.iter().filter(|m| m.error.is_some()) {
+                let step = if failed.target == "failed" {
+                    "subscribe the target to"
+                } else {
+                    "unsubscribe the source from"
+                };
+                println!(
+                    "Failed to {step}: {} ({})",
+                    failed.channel.channel_id, failed.channel.channel_url
+                );
+                println!(
+                    "With Error: {}",
+                    failed.error.as_deref().unwrap_or_default()
+                );
+            }
+
+            let count =
+                |f: &dyn Fn(&MigratedChannel) -> bool| migrated.iter().filter(|m| f(m)).count();
+            println!(
+                "{}/{} channels moved ({} were already subscribed), {} unsubscribed from the source.",
+                count(&|m| m.target != "failed"),
+                migrated.len(),
+                count(&|m| m.target == "already-subscribed"),
+                count(&|m| m.source == "unsubscribed")
+            );
+        }
+        OutputFormat::Json => {
+            let json: Vec<_> = migrated
+                .iter()
+                .map(|migrated| {
+                    json!({
+                        "channel_id": migrated.channel.channel_id,
+                        "channel_url": migrated.channel.channel_url,
+                        "channel_title": migrated.channel.channel_title,
+                        "target": migrated.target,
+                        "source": migrated.source,
+                        "error": migrated.error,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel the source was subscribed to.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record([
+                "channel_url",
+                "channel_id",
+                "channel_title",
+                "target",
+                "source",
+                "error",
+            ])?;
+            for migrated in migrated {
+                writer.write_record([
+                    &migrated.channel.channel_url,
+                    &migrated.channel.channel_id,
+                    &migrated.channel.channel_title,
+                    migrated.target,
+                    migrated.source,
+                    migrated.error.as_deref().unwrap_or_default(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    if migrated.iter().any(|migrated| migrated.error.is_some()) {
+        return Err(anyhow!("Some channels could not be migrated").into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::youtube::FailedYoutubeSubscription;
+
+    fn channel(channel_id: &str) -> YouTubeChannel {
+        YouTubeChannel {
+            channel_id: channel_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn failed(channel_id: &str) -> FailedYoutubeSubscription {
+        FailedYoutubeSubscription {
+            channel_url: String::new(),
+            channel_id: channel_id.to_string(),
+            error: "quotaExceeded".into(),
+        }
+    }
+
+    #[test]
+    fn report_tracks_both_accounts_per_channel() {
+        // arrange
+        let channels = vec![channel("a"), channel("b"), channel("c"), channel("d")];
+        let subscribed = YouTubeSubscriptionResult {
+            failed: vec![failed("c")],
+            already_subscribed: vec![channel("b")],
+            ..Default::default()
+        };
+        let unsubscribed = YouTubeSubscriptionResult {
+            failed: vec![failed("d")],
+            ..Default::default()
+        };
+
+        // act
+        let mut migrated = migrated_channels(&channels, &subscribed);
+        apply_unsubscribed(&mut migrated, &unsubscribed);
+
+        // assert
+        let outcomes: Vec<_> = migrated
+            .iter()
+            .map(|m| (m.channel.channel_id.as_str(), m.target, m.source))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("a", "subscribed", "unsubscribed"),
+                ("b", "already-subscribed", "unsubscribed"),
+                ("c", "failed", "kept"),
+                ("d", "subscribed", "failed"),
+            ]
+        );
+        assert!(migrated[2].error.is_some() && migrated[3].error.is_some());
+    }
+}
@@ -0,0 +1,103 @@
+use super::{
+    access_token, bind_channel, oauth2_service, require, resolve_profile, token_store,
+    youtube_repo, Requirement,
+};
+use crate::{
+    api::youtube_service::YouTubeService,
+    cli::ProfileCommand,
+    consts,
+    models::{config::Settings, profile::Profile},
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use anyhow::anyhow;
+
+/// Binding a profile only looks up the account's channel.
+const SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+
+pub async fn run<P>(
+    command: ProfileCommand,
+    current: &str,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    match command {
+        ProfileCommand::List => {
+            let profiles = profiles.list()?;
+            if profiles.is_empty() {
+                println!("No profiles yet, add one with `profile add <name>`.");
+            }
+            for profile in profiles {
+                println!("{}\t{}", profile.name, profile.binding());
+            }
+        }
+        ProfileCommand::Add {
+            name,
+            client_secret_file,
+        } => {
+            if profiles.get(&name)?.is_some() {
+                return Err(anyhow!("Profile `{name}` already exists").into());
+            }
+
+            let mut profile = Profile {
+                client_secret_file: client_secret_file.map(|path| path.display().to_string()),
+                ..Profile::new(&name)
+            };
+            add(&mut profile, settings, profiles).await?;
+            println!("Added profile `{name}`, bound to {}", profile.binding());
+        }
+        ProfileCommand::Remove { name } => {
+            if !profiles.remove(&name)? {
+                return Err(anyhow!("Unknown profile `{name}`").into());
+            }
+            token_store(settings)?.delete(&name)?;
+            println!("Removed profile `{name}` and its cached tokens.");
+        }
+        ProfileCommand::Show { name } => {
+            let profile = resolve_profile(profiles, name.as_deref().unwrap_or(current))?;
+            println!("Profile: {}", profile.name);
+            println!("Channel: {}", profile.binding());
+            if let Some(path) = &profile.client_secret_file {
+                println!("Client secret file: {path}");
+            }
+
+            match token_store(settings)?.load(&profile.name)? {
+                Some(token) => {
+                    println!(
+                        "Token: cached, refresh token {}",
+                        if token.refresh_token.is_some() {
+                            "present"
+                        } else {
+                            "missing"
+                        }
+                    );
+                    println!("Scopes: {}", token.scopes.join(" "));
+                }
+                None => println!("Token: none cached"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Authorizes `profile` and records the channel it is bound to.
+async fn add<P>(
+    profile: &mut Profile,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    require(settings, profile, &[Requirement::OAuthClient])?;
+    let oauth2_service = oauth2_service(profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, profile, settings, SCOPES).await?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, profile, profiles).await
+}
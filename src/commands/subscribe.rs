@@ -0,0 +1,255 @@
+use super::{
+    access_token, api_key, bind_channel, oauth2_service, read_channels, require, resolve_channels,
+    resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::{interfaces::t_youtube_service::TYouTubeService, youtube_service::YouTubeService},
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{
+        config::Settings,
+        youtube::{YouTubeSubscriptionPlan, YouTubeSubscriptionResult},
+    },
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use serde_json::json;
+use std::{io, path::Path};
+
+/// Subscribing changes the account, which needs the full scope.
+const SCOPES: &[&str] = &[consts::YOUTUBE_SCOPE];
+/// A dry run only reads the account's subscriptions.
+const DRY_RUN_SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+
+/// Subscribes the profile to every channel listed in `file`. A dry run prints the plan instead.
+pub async fn run<P>(
+    file: &Path,
+    dry_run: bool,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    let (requirements, scopes) = if dry_run {
+        (&[Requirement::OAuthClient][..], DRY_RUN_SCOPES)
+    } else {
+        (&[Requirement::ApiKey, Requirement::OAuthClient][..], SCOPES)
+    };
+    require(settings, &profile, requirements)?;
+
+    let channels = read_channels(file)?;
+
+    // Instantiate the dependnecies.
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = oauth2_service(&profile, settings)?;
+
+    // Retrieve the token from the cache, or from Google via OAuth
+    let mut secrets = access_token(&oauth2_service, &profile, settings, scopes).await?;
+
+    // Pass in dependnecies for the service
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    // Binding saves the profile, which a dry run leaves alone.
+    if !dry_run {
+        bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+    }
+    let (channels, _) = resolve_channels(&api, channels, settings, args).await?;
+
+    if dry_run {
+        args.info(format!(
+            "Dry run: planning {} channels for profile `{}`, nothing is changed",
+            channels.len(),
+            profile.name
+        ));
+        let mut plan = api.plan_subscriptions(&channels, &mut secrets).await?;
+        plan.lookup_cost = repo.lookup_cost();
+        return print_plan(&plan, args.output);
+    }
+
+    let api_key = api_key(settings)?;
+    args.info(format!(
+        "Subscribing profile `{}` to {} channels",
+        profile.name,
+        channels.len()
+    ));
+    args.detail(format!(
+        "Profile `{}` is bound to {}",
+        profile.name,
+        profile.binding()
+    ));
+
+    // NOTE: Can't subscribe to more than 200 channels in a day (10k token limit from Google)
+    let result = api.subscribe(api_key, &channels, &mut secrets).await?;
+
+    print_result(&result, args.output)
+}
+
+/// Prints what subscribing would do per channel, and the quota it would cost.
+fn print_plan(
+    plan: &YouTubeSubscriptionPlan,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let actions = [
+        ("subscribe", &plan.to_subscribe),
+        ("already-subscribed", &plan.already_subscribed),
+        ("duplicate", &plan.duplicates),
+        ("invalid", &plan.invalid),
+    ];
+    let read_cost = plan.pages_read * consts::LIST_QUOTA_COST + plan.lookup_cost;
+
+    match output {
+        OutputFormat::Text => {
+            for (action, channels) in actions {
+                for channel in channels {
+                    println!(
+                        "{action:<18} {} {}",
+                        channel.channel_id,
+                        if channel.channel_title.is_empty() {
+                            &channel.channel_url
+                        } else {
+                            &channel.channel_title
+                        }
+                    );
+                }
+            }
+            println!(
+                "{} to subscribe, {} already subscribed, {} duplicates, {} without a channel id",
+                plan.to_subscribe.len(),
+                plan.already_subscribed.len(),
+                plan.duplicates.len(),
+                plan.invalid.len()
+            );
+            println!(
+                "Estimated quota: {} units of {} per day (this dry run used {read_cost}, {} of \
+                 them looking up urls)",
+                plan.quota_cost(),
+                consts::DAILY_QUOTA,
+                plan.lookup_cost
+            );
+            if plan.quota_cost() > consts::DAILY_QUOTA {
+                println!("This exceeds the daily quota, split the file over several days.");
+            }
+        }
+        OutputFormat::Json => {
+            let channels: Vec<_> = actions
+                .iter()
+                .flat_map(|(action, channels)| {
+                    channels.iter().map(move |channel| {
+                        json!({
+                            "action": action,
+                            "channel_id": channel.channel_id,
+                            "channel_url": channel.channel_url,
+                            "channel_title": channel.channel_title,
+                        })
+                    })
+                })
+                .collect();
+            let json = json!({
+                "channels": channels,
+                "quota_cost": plan.quota_cost(),
+                "lookup_quota_cost": plan.lookup_cost,
+                "dry_run_quota_cost": read_cost,
+                "daily_quota": consts::DAILY_QUOTA,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel of the input file.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["action", "channel_url", "channel_id", "channel_title"])?;
+            for (action, channels) in actions {
+                for channel in channels {
+                    writer.write_record([
+                        action,
+                        &channel.channel_url,
+                        &channel.channel_id,
+                        &channel.channel_title,
+                    ])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_result(
+    result: &YouTubeSubscriptionResult,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Text => {
+            for failed_sub in &result.failed {
+                println!(
+                    "Failed to subscribe to: {} ({})",
+                    failed_sub.channel_id, failed_sub.channel_url
+                );
+                println!("With Error: {}", failed_sub.error);
+            }
+            println!("{}/{} successful subs!", result.successful, result.expected);
+            if !result.already_subscribed.is_empty() {
+                println!(
+                    "{} channels were already subscribed to and skipped.",
+                    result.already_subscribed.len()
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let failed: Vec<_> = result
+                .failed
+                .iter()
+                .map(|failed_sub| {
+                    json!({
+                        "channel_id": failed_sub.channel_id,
+                        "channel_url": failed_sub.channel_url,
+                        "error": failed_sub.error.to_string(),
+                    })
+                })
+                .collect();
+            let already_subscribed: Vec<_> = result
+                .already_subscribed
+                .iter()
+                .map(|channel| {
+                    json!({
+                        "channel_id": channel.channel_id,
+                        "channel_url": channel.channel_url,
+                    })
+                })
+                .collect();
+            let json = json!({
+                "expected": result.expected,
+                "successful": result.successful,
+                "failed": failed,
+                "already_subscribed": already_subscribed,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel that wasn't newly subscribed to.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["channel_url", "channel_id", "status", "error"])?;
+            for failed_sub in &result.failed {
+                writer.write_record([
+                    failed_sub.channel_url.as_str(),
+                    failed_sub.channel_id.as_str(),
+                    "failed",
+                    &failed_sub.error.to_string(),
+                ])?;
+            }
+            for channel in &result.already_subscribed {
+                writer.write_record([
+                    channel.channel_url.as_str(),
+                    channel.channel_id.as_str(),
+                    "already-subscribed",
+                    "",
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
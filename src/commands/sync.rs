@@ -0,0 +1,280 @@
+use super::{
+    access_token, api_key, bind_channel, confirm, oauth2_service, read_channels, require,
+    resolve_channels, resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::{interfaces::t_youtube_service::TYouTubeService, youtube_service::YouTubeService},
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{
+        config::Settings,
+        youtube::{YouTubeSubscriptionResult, YouTubeSyncPlan},
+    },
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use anyhow::anyhow;
+use serde_json::json;
+use std::{io, path::Path};
+
+/// Planning only reads the account.
+const PLAN_SCOPES: &[&str] = &[consts::YOUTUBE_READONLY_SCOPE];
+/// Applying changes the account, which needs the full scope.
+const APPLY_SCOPES: &[&str] = &[consts::YOUTUBE_SCOPE];
+
+/// Prints how to converge the profile's subscriptions towards the channels in `file`.
+pub async fn plan<P>(
+    file: &Path,
+    add_only: bool,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    require(settings, &profile, &[Requirement::OAuthClient])?;
+    let channels = read_channels(file)?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, PLAN_SCOPES).await?;
+
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+
+    let (channels, _) = resolve_channels(&api, channels, settings, args).await?;
+    let mut plan = api.plan_sync(&channels, &mut secrets).await?;
+    if add_only {
+        plan.to_unsubscribe.clear();
+    }
+
+    print_plan(&plan, args.output)
+}
+
+/// Converges the profile's subscriptions towards the channels in `file`, after showing the plan
+/// and asking for confirmation unless `yes` is given.
+pub async fn apply<P>(
+    file: &Path,
+    add_only: bool,
+    yes: bool,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    require(
+        settings,
+        &profile,
+        &[Requirement::ApiKey, Requirement::OAuthClient],
+    )?;
+    let api_key = api_key(settings)?;
+    let channels = read_channels(file)?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, APPLY_SCOPES).await?;
+
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+
+    let (channels, unresolved) = resolve_channels(&api, channels, settings, args).await?;
+    let mut plan = api.plan_sync(&channels, &mut secrets).await?;
+    if add_only {
+        plan.to_unsubscribe.clear();
+    }
+
+    // A row we can't read would otherwise have its channel unsubscribed from.
+    let unreadable = plan.subscribe.invalid.len() + unresolved;
+    if unreadable > 0 && !add_only {
+        return Err(anyhow!(
+            "{unreadable} rows of {} have no channel id or could not be resolved; fix them, or \
+             pass --add-only",
+            file.display()
+        )
+        .into());
+    }
+    if plan.subscribe.to_subscribe.is_empty() && plan.to_unsubscribe.is_empty() {
+        args.info(format!(
+            "Profile `{}` already matches {}, nothing to do.",
+            profile.name,
+            file.display()
+        ));
+        return Ok(());
+    }
+
+    // The plan goes to stderr here, stdout is left for the results.
+    if !yes {
+        eprint!("{}", plan_text(&plan));
+        if !confirm(&format!("Apply this plan to profile `{}`?", profile.name))? {
+            return Err(anyhow!("Aborted, nothing was changed").into());
+        }
+    }
+
+    // Carry out the plan that was confirmed, instead of listing the subscriptions again.
+    let added = api
+        .apply_subscriptions(api_key, plan.subscribe, &mut secrets)
+        .await?;
+    let removed = api
+        .remove_subscriptions(api_key, &plan.to_unsubscribe, &mut secrets)
+        .await?;
+
+    print_applied(&added, &removed, args.output)
+}
+
+/// The plan as `+` and `-` lines, followed by a summary.
+fn plan_text(plan: &YouTubeSyncPlan) -> String {
+    let mut text = String::new();
+    for channel in &plan.subscribe.to_subscribe {
+        text += &format!("+ {} {}\n", channel.channel_id, channel.channel_title);
+    }
+    for subscription in &plan.to_unsubscribe {
+        text += &format!(
+            "- {} {}\n",
+            subscription.channel_id, subscription.channel_title
+        );
+    }
+    for channel in &plan.subscribe.invalid {
+        text += &format!("! no channel id: {}\n", channel.channel_url);
+    }
+
+    text += &format!(
+        "{} to add, {} to remove, {} unchanged. Estimated quota: {} units of {} per day.\n",
+        plan.subscribe.to_subscribe.len(),
+        plan.to_unsubscribe.len(),
+        plan.subscribe.already_subscribed.len(),
+        plan.quota_cost(),
+        consts::DAILY_QUOTA
+    );
+    text
+}
+
+fn print_plan(
+    plan: &YouTubeSyncPlan,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let to_unsubscribe: Vec<_> = plan
+        .to_unsubscribe
+        .iter()
+        .map(|subscription| subscription.channel())
+        .collect();
+    let actions = [
+        ("add", &plan.subscribe.to_subscribe),
+        ("remove", &to_unsubscribe),
+        ("unchanged", &plan.subscribe.already_subscribed),
+        ("invalid", &plan.subscribe.invalid),
+    ];
+
+    match output {
+        OutputFormat::Text => print!("{}", plan_text(plan)),
+        OutputFormat::Json => {
+            let channels: Vec<_> = actions
+                .iter()
+                .flat_map(|(action, channels)| {
+                    channels.iter().map(move |channel| {
+                        json!({
+                            "action": action,
+                            "channel_id": channel.channel_id,
+                            "channel_url": channel.channel_url,
+                            "channel_title": channel.channel_title,
+                        })
+                    })
+                })
+                .collect();
+            let json = json!({
+                "channels": channels,
+                "quota_cost": plan.quota_cost(),
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel that is listed or subscribed to.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["action", "channel_url", "channel_id", "channel_title"])?;
+            for (action, channels) in actions {
+                for channel in channels {
+                    writer.write_record([
+                        action,
+                        &channel.channel_url,
+                        &channel.channel_id,
+                        &channel.channel_title,
+                    ])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_applied(
+    added: &YouTubeSubscriptionResult,
+    removed: &YouTubeSubscriptionResult,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    let results = [("add", added), ("remove", removed)];
+
+    match output {
+        OutputFormat::Text => {
+            for (action, result) in results {
+                for failed in &result.failed {
+                    println!(
+                        "Failed to {action}: {} ({})",
+                        failed.channel_id, failed.channel_url
+                    );
+                    println!("With Error: {}", failed.error);
+                }
+            }
+            println!(
+                "Added {}/{}, removed {}/{}.",
+                added.successful, added.expected, removed.successful, removed.expected
+            );
+        }
+        OutputFormat::Json => {
+            let mut json = json!({});
+            for (action, result) in results {
+                let failed: Vec<_> = result
+                    .failed
+                    .iter()
+                    .map(|failed| {
+                        json!({
+                            "channel_id": failed.channel_id,
+                            "channel_url": failed.channel_url,
+                            "error": failed.error.to_string(),
+                        })
+                    })
+                    .collect();
+                json[action] = json!({
+                    "expected": result.expected,
+                    "successful": result.successful,
+                    "failed": failed,
+                });
+            }
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per change that failed.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["action", "channel_url", "channel_id", "error"])?;
+            for (action, result) in results {
+                for failed in &result.failed {
+                    writer.write_record([
+                        action,
+                        &failed.channel_url,
+                        &failed.channel_id,
+                        &failed.error.to_string(),
+                    ])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use super::{
+    access_token, api_key, bind_channel, oauth2_service, read_channels, require, resolve_channels,
+    resolve_profile, youtube_repo, Requirement,
+};
+use crate::{
+    api::{interfaces::t_youtube_service::TYouTubeService, youtube_service::YouTubeService},
+    cli::{GlobalArgs, OutputFormat},
+    consts,
+    models::{config::Settings, youtube::YouTubeSubscriptionResult},
+    tools::{csv_writer::CSVWriter, interfaces::t_profile_store::TProfileStore},
+};
+use serde_json::json;
+use std::{io, path::Path};
+
+/// Unsubscribing changes the account, which needs the full scope.
+const SCOPES: &[&str] = &[consts::YOUTUBE_SCOPE];
+
+/// Unsubscribes the profile from every channel listed in `file`.
+pub async fn run<P>(
+    file: &Path,
+    args: &GlobalArgs,
+    settings: &Settings,
+    profiles: &P,
+) -> anyhow::Result<(), Box<dyn std::error::Error>>
+where
+    P: TProfileStore,
+{
+    let mut profile = resolve_profile(profiles, &args.profile)?;
+    require(
+        settings,
+        &profile,
+        &[Requirement::ApiKey, Requirement::OAuthClient],
+    )?;
+    let api_key = api_key(settings)?;
+
+    let channels = read_channels(file)?;
+
+    let writer = CSVWriter::default();
+    let repo = youtube_repo(settings);
+    let oauth2_service = oauth2_service(&profile, settings)?;
+    let mut secrets = access_token(&oauth2_service, &profile, settings, SCOPES).await?;
+
+    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
+    bind_channel(&api, &mut secrets, &mut profile, profiles).await?;
+    let (channels, _) = resolve_channels(&api, channels, settings, args).await?;
+
+    args.info(format!(
+        "Unsubscribing profile `{}` from {} channels",
+        profile.name,
+        channels.len()
+    ));
+
+    let result = api.unsubscribe(api_key, &channels, &mut secrets).await?;
+
+    print_result(&result, args.output)
+}
+
+fn print_result(
+    result: &YouTubeSubscriptionResult,
+    output: OutputFormat,
+) -> anyhow::Result<(), Box<dyn std::error::Error>> {
+    match output {
+        OutputFormat::Text => {
+            for failed_unsub in &result.failed {
+                println!(
+                    "Failed to unsubscribe from: {} ({})",
+                    failed_unsub.channel_id, failed_unsub.channel_url
+                );
+                println!("With Error: {}", failed_unsub.error);
+            }
+            println!(
+                "{}/{} successful unsubs!",
+                result.successful, result.expected
+            );
+            if !result.not_subscribed.is_empty() {
+                println!(
+                    "{} channels weren't subscribed to and were skipped.",
+                    result.not_subscribed.len()
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let failed: Vec<_> = result
+                .failed
+                .iter()
+                .map(|failed_unsub| {
+                    json!({
+                        "channel_id": failed_unsub.channel_id,
+                        "channel_url": failed_unsub.channel_url,
+                        "error": failed_unsub.error.to_string(),
+                    })
+                })
+                .collect();
+            let not_subscribed: Vec<_> = result
+                .not_subscribed
+                .iter()
+                .map(|channel| {
+                    json!({
+                        "channel_id": channel.channel_id,
+                        "channel_url": channel.channel_url,
+                    })
+                })
+                .collect();
+            let json = json!({
+                "expected": result.expected,
+                "successful": result.successful,
+                "failed": failed,
+                "not_subscribed": not_subscribed,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        // One row per channel that wasn't unsubscribed from.
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout());
+            writer.write_record(["channel_url", "channel_id", "status", "error"])?;
+            for failed_unsub in &result.failed {
+                writer.write_record([
+                    failed_unsub.channel_url.as_str(),
+                    failed_unsub.channel_id.as_str(),
+                    "failed",
+                    &failed_unsub.error.to_string(),
+                ])?;
+            }
+            for channel in &result.not_subscribed {
+                writer.write_record([
+                    channel.channel_url.as_str(),
+                    channel.channel_id.as_str(),
+                    "not-subscribed",
+                    "",
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
@@ -3,4 +3,56 @@ pub const CLIENT_ID: &str = "CLIENT_ID";
 pub const AUTH_URI: &str = "AUTH_URI";
 pub const TOKEN_URI: &str = "TOKEN_URI";
 pub const REDIRECT_URI: &str = "REDIRECT_URI";
+pub const DEVICE_AUTH_URI: &str = "DEVICE_AUTH_URI";
+pub const REVOKE_URI: &str = "REVOKE_URI";
 pub const CLIENT_SECRET: &str = "CLIENT_SECRET";
+pub const CLIENT_SECRET_FILE: &str = "CLIENT_SECRET_FILE";
+pub const OAUTH_REDIRECT_TIMEOUT_SECS: &str = "OAUTH_REDIRECT_TIMEOUT_SECS";
+pub const OAUTH_EPHEMERAL_PORT: &str = "OAUTH_EPHEMERAL_PORT";
+pub const OAUTH_FLOW: &str = "OAUTH_FLOW";
+pub const TOKEN_STORE: &str = "TOKEN_STORE";
+pub const TOKEN_STORE_PASSPHRASE: &str = "TOKEN_STORE_PASSPHRASE";
+pub const SECRET_TOOL: &str = "SECRET_TOOL";
+pub const CHANNEL_ID_TTL_DAYS: &str = "CHANNEL_ID_TTL_DAYS";
+pub const CHANNEL_METADATA_TTL_DAYS: &str = "CHANNEL_METADATA_TTL_DAYS";
+
+/// Full access to the account, needed to change subscriptions.
+pub const YOUTUBE_SCOPE: &str = "https://www.googleapis.com/auth/youtube";
+/// Read-only access to the account, enough to look up its channel and subscriptions.
+pub const YOUTUBE_READONLY_SCOPE: &str = "https://www.googleapis.com/auth/youtube.readonly";
+
+/// Videos fetched per channel when `--max-results` is not given; a single page of results.
+pub const MAX_RESULTS: i32 = 50;
+
+/// Results the API returns per page at most.
+pub const PAGE_SIZE: usize = 50;
+/// Quota units a `subscriptions.insert` call costs.
+pub const SUBSCRIBE_QUOTA_COST: u32 = 50;
+/// Quota units a `subscriptions.delete` call costs.
+pub const UNSUBSCRIBE_QUOTA_COST: u32 = 50;
+/// Quota units a page of any `list` call costs.
+pub const LIST_QUOTA_COST: u32 = 1;
+/// Quota units a project gets per day, unless Google granted it more.
+pub const DAILY_QUOTA: u32 = 10_000;
+
+/// Name of the directory this tool keeps its state in, under the XDG config dir.
+pub const APP_NAME: &str = "youtube-auto-sub";
+/// Per-user config file, in the config dir.
+pub const CONFIG_FILE: &str = "config.toml";
+/// Per-project config file, in the working directory.
+pub const PROJECT_CONFIG_FILE: &str = "youtube-auto-sub.toml";
+/// Cache of channel lookups, in the cache dir.
+pub const CHANNEL_CACHE_FILE: &str = "channels.json";
+/// Days a url stays resolved to the same channel id when `CHANNEL_ID_TTL_DAYS` is not set.
+/// Handles can be given up and claimed by another channel, so this is not forever.
+pub const DEFAULT_CHANNEL_ID_TTL_DAYS: u64 = 30;
+/// Days a channel's title is reused when `CHANNEL_METADATA_TTL_DAYS` is not set.
+pub const DEFAULT_CHANNEL_METADATA_TTL_DAYS: u64 = 7;
+/// Account the token cache is keyed by when none is given.
+pub const DEFAULT_ACCOUNT: &str = "default";
+/// Google's device authorization endpoint, used when `DEVICE_AUTH_URI` is not set.
+pub const DEFAULT_DEVICE_AUTH_URI: &str = "https://oauth2.googleapis.com/device/code";
+/// Google's token revocation endpoint, used when `REVOKE_URI` is not set.
+pub const DEFAULT_REVOKE_URI: &str = "https://oauth2.googleapis.com/revoke";
+/// The libsecret CLI used to reach the keyring, used when `SECRET_TOOL` is not set.
+pub const DEFAULT_SECRET_TOOL: &str = "secret-tool";
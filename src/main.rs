@@ -1,112 +1,80 @@
+#![allow(async_fn_in_trait)]
+
 pub mod api;
+pub(crate) mod cli;
+pub(crate) mod commands;
 pub(crate) mod consts;
 pub(crate) mod models;
 pub(crate) mod tools;
 
-use anyhow::anyhow;
-use api::{
-    interfaces::{t_oauth2_service::TOAuth2Service, t_youtube_service::TYouTubeService},
-    oauth2_service::OAuth2Service,
-    youtube_repo::YouTubeRepository,
-    youtube_service::YouTubeService,
-};
-use dotenv::dotenv;
-use models::{oath_2::OauthSecrets, youtube::YouTubeChannel};
-use std::{
-    env::{self},
-    ffi::OsString,
-    fs::File,
-};
-use tokio::task::spawn_blocking;
-use tools::{csv_reader::CSVReader, csv_writer::CSVWriter, interfaces::t_csv_reader::TCSVReader};
-
-const MAX_RESULTS: i32 = 1;
-
-fn get_first_arg() -> Result<OsString, anyhow::Error> {
-    match env::args_os().nth(1) {
-        None => Err(anyhow!("expected 1 argument!").into()),
-        Some(file_path) => Ok(file_path),
-    }
-}
+use clap::Parser;
+use cli::{Cli, Command};
+use tools::profile_store::ProfileStore;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<(), Box<dyn std::error::Error>> {
-    // Load environemnt variables from .env file
-    dotenv().ok();
-
-    let file_path = get_first_arg()?;
-    let file = File::open(file_path)?;
-    let channels: Vec<YouTubeChannel> = CSVReader::read_records(&file)?;
+    let cli = Cli::parse();
 
-    // Fetch API key from env variables
-    let api_key = env::var(consts::YOUTUBE_API_KEY).expect("YOUTUBE_API_KEY must be set!");
+    // Load environemnt variables from .env, which the config files are merged under
+    dotenv::dotenv().ok();
 
-    // Fech OAuth secrets from env file
-    let mut secrets = OauthSecrets {
-        client_id: env::var(consts::CLIENT_ID).expect("CLIENT_ID must be set!"),
-        client_secret: env::var(consts::CLIENT_SECRET).expect("CLIENT_SECRET must be set!"),
-        auth_url: env::var(consts::AUTH_URI).expect("AUTH_URI must be set!"),
-        token_url: env::var(consts::TOKEN_URI).expect("TOKEN_URI must be set!"),
-        redirect_url: env::var(consts::REDIRECT_URI).expect("REDIRECT_URI must be set!"),
-        access_token: String::from(""),
-    };
+    let args = &cli.global;
+    let profiles = ProfileStore::default();
 
-    // TODO: add a set_headers(headers) method to the CSVWriter so i don't have to pass it to the
-    // constructor.
-    // let headers = vec![
-    //     "Video ID".to_owned(),
-    //     "Title".to_owned(),
-    //     "Description".to_owned(),
-    //     "Published At".to_owned(),
-    // ];
-
-    // Instantiate the dependnecies.
-    let writer = CSVWriter::default();
-    let repo = YouTubeRepository::default();
-    let oauth2_service = OAuth2Service::default();
-
-    // Retrieve the token_secrets from Google via Oauth
-    let token_secrets =
-        spawn_blocking(move || OAuth2Service::request_access_token(&mut secrets)).await?;
+    // The doctor reports configuration problems as failed checks instead of bailing out on them.
+    if let Command::Doctor = cli.command {
+        return commands::doctor::run(args, &profiles).await;
+    }
+    let settings = commands::load_settings(args)?;
 
-    let mut token_secrets = match token_secrets {
-        Ok(it) => it,
-        _ => {
-            return Err(anyhow!("Could not retrieve token secrets!").into());
+    match cli.command {
+        Command::Subscribe { file, dry_run } => {
+            commands::subscribe::run(&file, dry_run, args, &settings, &profiles).await
         }
-    };
-
-    // Pass in dependnecies for the service
-    let api = YouTubeService::new(&writer, &repo, &oauth2_service);
-
-    /* Writing videos to file
-        let videos = api.get_videos(&api_key, channel_id, MAX_RESULTS).await?;
-
-        Write the videos to file
-        api.write_to_csv(videos);
-
-         let channels =  vec![YouTubeChannel{
-             channel_url: "url".to_string(),
-             // channel_id: "UC_x5XG1OV2P6uZZ5FSM9Ttw".to_string()
-             channel_id: "UCmXIqVsp5QWiVDpyBP32O0Q".to_string(),
-             channel_title: String::new()
-         }];
-    */
-
-    // Attempt to subscribe to channel
-    // NOTE: Can't subscribe to more than 200 channels in a day (10k token limit from Google)
-    // let result = api.subscribe(&api_key, &channels, &mut token_secrets).await?;
-    // let videos = api.get_videos("api_key", "chanel id", MAX_RESULTS).await?;
-
-    let result = api
-        .subscribe(&api_key, &channels, &mut token_secrets)
-        .await?;
-
-    for failed_sub in result.failed {
-        println!("Failed to subscribe to: {}", failed_sub.channel_id);
-        eprintln!("With Error: {:?}", failed_sub.error);
+        Command::Unsubscribe { file } => {
+            commands::unsubscribe::run(&file, args, &settings, &profiles).await
+        }
+        Command::ExportSubscriptions { file } => {
+            commands::export_subscriptions::run(file.as_deref(), args, &settings, &profiles).await
+        }
+        Command::FetchVideos(fetch_args) => {
+            commands::fetch_videos::run(&fetch_args, args, &settings).await
+        }
+        Command::Plan { file, add_only } => {
+            commands::sync::plan(&file, add_only, args, &settings, &profiles).await
+        }
+        Command::Apply {
+            file,
+            add_only,
+            yes,
+        } => commands::sync::apply(&file, add_only, yes, args, &settings, &profiles).await,
+        Command::Migrate {
+            from,
+            to,
+            unsubscribe_source,
+            yes,
+        } => {
+            let from = from.as_deref().unwrap_or(&args.profile);
+            commands::migrate::run(
+                from,
+                &to,
+                unsubscribe_source,
+                yes,
+                args,
+                &settings,
+                &profiles,
+            )
+            .await
+        }
+        Command::Diff { a, b } => commands::diff::run(&a, &b, args, &settings, &profiles).await,
+        Command::Auth { read_only } => {
+            commands::auth::run(read_only, args, &settings, &profiles).await
+        }
+        Command::Logout => commands::logout::run(&args.profile, &settings, &profiles).await,
+        Command::Doctor => unreachable!("handled before loading the settings"),
+        Command::Profile(command) => {
+            commands::profile::run(command, &args.profile, &settings, &profiles).await
+        }
+        Command::Cache(command) => commands::cache::run(command, args, &settings),
     }
-    println!("{} successful subs!", result.successful);
-
-    Ok(())
 }
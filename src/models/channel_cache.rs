@@ -0,0 +1,30 @@
+use std::fmt;
+
+/// What a channel cache entry remembers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCacheKind {
+    /// The channel id a url resolved to, keyed by the url.
+    Id,
+    /// A channel's title, keyed by the channel id.
+    Metadata,
+}
+
+impl fmt::Display for ChannelCacheKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Id => "id",
+            Self::Metadata => "metadata",
+        })
+    }
+}
+
+/// One entry of the channel cache, expired or not.
+#[derive(Debug, Clone)]
+pub struct ChannelCacheEntry {
+    pub kind: ChannelCacheKind,
+    pub key: String,
+    pub value: String,
+    /// When the entry was cached, in seconds since the unix epoch.
+    pub cached_at: u64,
+    pub expired: bool,
+}
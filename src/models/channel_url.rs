@@ -0,0 +1,190 @@
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use url::Url;
+
+/// Hosts YouTube serves channel and video pages on.
+const YOUTUBE_HOSTS: [&str; 4] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "music.youtube.com",
+];
+
+/// Top-level pages that are not legacy `youtube.com/<custom name>` channel urls.
+const RESERVED_PATHS: [&str; 8] = [
+    "feed", "playlist", "results", "watch", "shorts", "live", "embed", "account",
+];
+
+/// The forms a YouTube url can point at a channel in. Path segments are percent-decoded, so
+/// they hold the handle or name itself and must be encoded again before going into a url.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChannelUrl {
+    /// `/channel/UC…`, which holds the channel id itself.
+    Id(String),
+    /// `/@handle`, without the `@`.
+    Handle(String),
+    /// `/c/customname`, or the legacy `youtube.com/customname`.
+    CustomName(String),
+    /// `/user/legacyname`.
+    Username(String),
+    /// A video of the channel: `watch?v=`, `/shorts/`, `/live/`, `/embed/` or `youtu.be/`.
+    Video(String),
+}
+
+impl ChannelUrl {
+    /// Recognizes the channel `url` points at. Urls may leave out the scheme, and a bare
+    /// `@handle` is accepted too.
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = url.trim();
+        if let Some(handle) = url.strip_prefix('@') {
+            return non_empty(handle).map(Self::Handle);
+        }
+
+        let url = if url.contains("://") {
+            Url::parse(url).ok()?
+        } else {
+            Url::parse(&format!("https://{url}")).ok()?
+        };
+        let host = url.host_str()?.to_ascii_lowercase();
+        let decoded: Vec<String> = url
+            .path_segments()
+            .map(|segments| {
+                segments
+                    .filter(|s| !s.is_empty())
+                    .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let segments: Vec<&str> = decoded.iter().map(String::as_str).collect();
+
+        if host == "youtu.be" {
+            return segments
+                .first()
+                .and_then(|id| non_empty(id))
+                .map(Self::Video);
+        }
+        if !YOUTUBE_HOSTS.contains(&host.as_str()) {
+            return None;
+        }
+
+        match segments.as_slice() {
+            ["channel", id, ..] => non_empty(id).map(Self::Id),
+            ["c", name, ..] => non_empty(name).map(Self::CustomName),
+            ["user", name, ..] => non_empty(name).map(Self::Username),
+            ["watch", ..] => url
+                .query_pairs()
+                .find(|(key, _)| key == "v")
+                .and_then(|(_, id)| non_empty(&id))
+                .map(Self::Video),
+            ["shorts" | "live" | "embed" | "v", id, ..] => non_empty(id).map(Self::Video),
+            [page, ..] if page.starts_with('@') => non_empty(&page[1..]).map(Self::Handle),
+            [name, ..] if !RESERVED_PATHS.contains(name) => non_empty(name).map(Self::CustomName),
+            _ => None,
+        }
+    }
+
+    /// The url in a single canonical form, so that every way of writing it is cached under
+    /// the same key. Handles are case-insensitive, so they are lower-cased.
+    pub fn canonical(&self) -> String {
+        match self {
+            Self::Id(id) => format!("https://www.youtube.com/channel/{id}"),
+            Self::Handle(handle) => {
+                format!("https://www.youtube.com/@{}", handle.to_ascii_lowercase())
+            }
+            Self::CustomName(name) => format!("https://www.youtube.com/c/{name}"),
+            Self::Username(name) => format!("https://www.youtube.com/user/{name}"),
+            Self::Video(id) => format!("https://youtu.be/{id}"),
+        }
+    }
+}
+
+impl fmt::Display for ChannelUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "channel {id}"),
+            Self::Handle(handle) => write!(f, "handle @{handle}"),
+            Self::CustomName(name) => write!(f, "custom url {name}"),
+            Self::Username(name) => write!(f, "user {name}"),
+            Self::Video(id) => write!(f, "video {id}"),
+        }
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_recognizes_every_url_form() {
+        let cases = [
+            (
+                "https://www.youtube.com/channel/UCabc/videos",
+                Some(ChannelUrl::Id("UCabc".to_string())),
+            ),
+            (
+                "youtube.com/@SomeHandle",
+                Some(ChannelUrl::Handle("SomeHandle".to_string())),
+            ),
+            (
+                "@SomeHandle",
+                Some(ChannelUrl::Handle("SomeHandle".to_string())),
+            ),
+            (
+                "https://m.youtube.com/c/CustomName",
+                Some(ChannelUrl::CustomName("CustomName".to_string())),
+            ),
+            (
+                "https://www.youtube.com/CustomName",
+                Some(ChannelUrl::CustomName("CustomName".to_string())),
+            ),
+            (
+                "http://www.youtube.com/user/legacy",
+                Some(ChannelUrl::Username("legacy".to_string())),
+            ),
+            (
+                "https://www.youtube.com/watch?list=PL1&v=dQw4w9WgXcQ",
+                Some(ChannelUrl::Video("dQw4w9WgXcQ".to_string())),
+            ),
+            (
+                "https://youtube.com/shorts/abc123",
+                Some(ChannelUrl::Video("abc123".to_string())),
+            ),
+            (
+                "https://youtu.be/dQw4w9WgXcQ?t=42",
+                Some(ChannelUrl::Video("dQw4w9WgXcQ".to_string())),
+            ),
+            (
+                "https://www.youtube.com/@caf%C3%A9",
+                Some(ChannelUrl::Handle("café".to_string())),
+            ),
+            ("https://www.youtube.com/playlist?list=PL1", None),
+            ("https://example.com/channel/UCabc", None),
+            ("", None),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(ChannelUrl::parse(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn canonical_is_the_same_for_every_spelling() {
+        let urls = [
+            "@SomeHandle",
+            "youtube.com/@somehandle",
+            "https://m.youtube.com/@SomeHandle/videos",
+        ];
+
+        for url in urls {
+            assert_eq!(
+                ChannelUrl::parse(url).unwrap().canonical(),
+                "https://www.youtube.com/@somehandle",
+                "{url}"
+            );
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use super::{
+    oath_2::{AuthFlow, TokenBackend},
+    secret::Secret,
+};
+use crate::consts;
+use std::{collections::BTreeMap, fmt, path::PathBuf, time::Duration};
+use url::Url;
+
+/// Every setting, by the name of its env variable. In TOML files the same names are used in
+/// lower case, e.g. `oauth_flow = "device"`.
+const KEYS: [&str; 17] = [
+    consts::YOUTUBE_API_KEY,
+    consts::CLIENT_SECRET_FILE,
+    consts::CLIENT_ID,
+    consts::CLIENT_SECRET,
+    consts::AUTH_URI,
+    consts::TOKEN_URI,
+    consts::REDIRECT_URI,
+    consts::DEVICE_AUTH_URI,
+    consts::REVOKE_URI,
+    consts::OAUTH_REDIRECT_TIMEOUT_SECS,
+    consts::OAUTH_EPHEMERAL_PORT,
+    consts::OAUTH_FLOW,
+    consts::TOKEN_STORE,
+    consts::TOKEN_STORE_PASSPHRASE,
+    consts::SECRET_TOOL,
+    consts::CHANNEL_ID_TTL_DAYS,
+    consts::CHANNEL_METADATA_TTL_DAYS,
+];
+
+/// Settings whose values must not be repeated in error messages.
+const SECRET_KEYS: [&str; 3] = [
+    consts::YOUTUBE_API_KEY,
+    consts::CLIENT_SECRET,
+    consts::TOKEN_STORE_PASSPHRASE,
+];
+
+/// Raw settings collected from config files, the environment and the command line. Every layer
+/// that is merged overrides the values of the layers merged before it.
+#[derive(Default)]
+pub struct Config {
+    /// Value of each setting and where it came from, keyed by env variable name.
+    values: BTreeMap<&'static str, (String, String)>,
+    /// Problems found while merging, reported together by `resolve`.
+    problems: Vec<String>,
+}
+
+impl Config {
+    /// Merges the TOML document `toml`, read from `origin`.
+    pub fn merge_toml(&mut self, toml: &str, origin: &str) {
+        let table: toml::Table = match toml.parse() {
+            Ok(table) => table,
+            Err(e) => {
+                self.problems.push(format!("{origin}: {}", e.message()));
+                return;
+            }
+        };
+
+        for (key, value) in table {
+            let Some(name) = KEYS
+                .into_iter()
+                .find(|name| name.eq_ignore_ascii_case(&key))
+            else {
+                self.problems.push(format!("{origin}: unknown key `{key}`"));
+                continue;
+            };
+
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                other => {
+                    self.problems.push(format!(
+                        "{origin}: `{key}` must be a string, number or boolean, not {}",
+                        other.type_str()
+                    ));
+                    continue;
+                }
+            };
+            self.values.insert(name, (value, origin.to_string()));
+        }
+    }
+
+    /// Merges the env variables named in `consts`, where `var` returns them.
+    pub fn merge_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        for name in KEYS {
+            if let Some(value) = var(name) {
+                self.values.insert(name, (value, format!("env {name}")));
+            }
+        }
+    }
+
+    /// Sets a single value, e.g. from a command line flag named `origin`.
+    pub fn set(&mut self, name: &'static str, value: &str, origin: &str) {
+        self.values
+            .insert(name, (value.to_string(), origin.to_string()));
+    }
+
+    /// Checks every value and returns the typed settings, or all problems found at once.
+    pub fn resolve(self) -> Result<Settings, ConfigError> {
+        let mut problems = self.problems;
+        let mut settings = Settings::default();
+
+        for (name, (value, origin)) in self.values {
+            let key = name.to_ascii_lowercase();
+            let mut invalid = |reason: String| {
+                if SECRET_KEYS.contains(&name) {
+                    problems.push(format!("`{key}` (from {origin}) {reason}"));
+                } else {
+                    problems.push(format!("`{key}` = \"{value}\" (from {origin}) {reason}"));
+                }
+            };
+
+            match name {
+                consts::YOUTUBE_API_KEY => settings.youtube_api_key = Some(value.into()),
+                consts::CLIENT_SECRET_FILE => settings.client_secret_file = Some(value),
+                consts::AUTH_URI
+                | consts::TOKEN_URI
+                | consts::REDIRECT_URI
+                | consts::DEVICE_AUTH_URI
+                | consts::REVOKE_URI => match Url::parse(&value) {
+                    Ok(_) => {
+                        settings.oauth_client.insert(name, value);
+                    }
+                    Err(e) => invalid(format!("is not a valid url: {e}")),
+                },
+                consts::CLIENT_ID | consts::CLIENT_SECRET => {
+                    settings.oauth_client.insert(name, value);
+                }
+                consts::OAUTH_REDIRECT_TIMEOUT_SECS => match value.parse() {
+                    Ok(secs) => settings.redirect_timeout = Some(Duration::from_secs(secs)),
+                    Err(_) => invalid("must be a number of seconds".to_string()),
+                },
+                consts::OAUTH_EPHEMERAL_PORT => match value.parse() {
+                    Ok(ephemeral_port) => settings.ephemeral_port = Some(ephemeral_port),
+                    Err(_) => invalid("must be true or false".to_string()),
+                },
+                consts::OAUTH_FLOW => match value.parse::<AuthFlow>() {
+                    Ok(flow) => settings.auth_flow = Some(flow),
+                    Err(e) => invalid(e),
+                },
+                consts::TOKEN_STORE => match value.parse::<TokenBackend>() {
+                    Ok(backend) => settings.token_store = backend,
+                    Err(e) => invalid(e),
+                },
+                consts::TOKEN_STORE_PASSPHRASE => {
+                    settings.token_store_passphrase = Some(value.into())
+                }
+                consts::SECRET_TOOL => settings.secret_tool = Some(PathBuf::from(value)),
+                consts::CHANNEL_ID_TTL_DAYS => match value.parse() {
+                    Ok(days) => settings.channel_id_ttl = Some(days_to_duration(days)),
+                    Err(_) => invalid("must be a number of days".to_string()),
+                },
+                consts::CHANNEL_METADATA_TTL_DAYS => match value.parse() {
+                    Ok(days) => settings.channel_metadata_ttl = Some(days_to_duration(days)),
+                    Err(_) => invalid("must be a number of days".to_string()),
+                },
+                _ => unreachable!("`{name}` is not in KEYS"),
+            }
+        }
+
+        if settings.token_store == TokenBackend::Encrypted
+            && settings.token_store_passphrase.is_none()
+        {
+            problems.push(
+                "`token_store` is `encrypted`, but `token_store_passphrase` is not set".to_string(),
+            );
+        }
+
+        if problems.is_empty() {
+            Ok(settings)
+        } else {
+            Err(ConfigError(problems))
+        }
+    }
+}
+
+/// Checked settings, see [`Config::resolve`].
+#[derive(Default)]
+pub struct Settings {
+    pub youtube_api_key: Option<Secret>,
+    pub client_secret_file: Option<String>,
+    /// Values overriding the OAuth client from the client secret file, keyed by env name.
+    oauth_client: BTreeMap<&'static str, String>,
+    pub redirect_timeout: Option<Duration>,
+    pub ephemeral_port: Option<bool>,
+    pub auth_flow: Option<AuthFlow>,
+    pub token_store: TokenBackend,
+    pub token_store_passphrase: Option<Secret>,
+    pub secret_tool: Option<PathBuf>,
+    /// How long a resolved channel id is cached; with 0 every url is looked up again.
+    pub channel_id_ttl: Option<Duration>,
+    /// How long a channel's title is cached.
+    pub channel_metadata_ttl: Option<Duration>,
+    /// Set from `--verbose` rather than a config key.
+    pub verbose: bool,
+}
+
+impl Settings {
+    /// Returns the OAuth client setting named `name` (e.g. `consts::CLIENT_ID`), if it was set.
+    pub fn oauth_client(&self, name: &str) -> Option<String> {
+        self.oauth_client.get(name).cloned()
+    }
+}
+
+/// `days` as a duration, saturating instead of overflowing.
+pub(crate) fn days_to_duration(days: u64) -> Duration {
+    Duration::from_secs(days.saturating_mul(24 * 60 * 60))
+}
+
+/// Every problem found in the configuration.
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_take_precedence() {
+        // arrange
+        let mut sut = Config::default();
+        sut.merge_toml(
+            "oauth_flow = \"manual\"\ntoken_uri = \"https://user/token\"",
+            "user",
+        );
+        sut.merge_toml(
+            "oauth_flow = \"device\"\noauth_ephemeral_port = true",
+            "project",
+        );
+        sut.merge_env(|name| (name == consts::TOKEN_URI).then(|| "https://env/token".to_string()));
+        sut.set(consts::OAUTH_FLOW, "loopback", "--auth-flow");
+
+        // act
+        let settings = sut.resolve().unwrap();
+
+        // assert
+        assert_eq!(settings.auth_flow, Some(AuthFlow::Loopback));
+        assert_eq!(settings.ephemeral_port, Some(true));
+        assert_eq!(
+            settings.oauth_client(consts::TOKEN_URI).as_deref(),
+            Some("https://env/token")
+        );
+    }
+
+    #[test]
+    fn resolve_reports_every_problem_without_leaking_secrets() {
+        // arrange
+        let mut sut = Config::default();
+        sut.merge_toml(
+            "oauth_redirect_timeout_secs = \"soon\"\nredirect_url = \"typo\"\ntoken_store = \"encrypted\"",
+            "config.toml",
+        );
+        sut.merge_env(|name| match name {
+            consts::AUTH_URI => Some("not a url".to_string()),
+            consts::OAUTH_FLOW => Some("carrier-pigeon".to_string()),
+            consts::YOUTUBE_API_KEY => Some("AIza-secret".to_string()),
+            _ => None,
+        });
+
+        // act
+        let error = sut.resolve().err().unwrap().to_string();
+
+        // assert
+        assert!(error.contains("unknown key `redirect_url`"));
+        assert!(error.contains("`oauth_redirect_timeout_secs` = \"soon\""));
+        assert!(error.contains("`auth_uri` = \"not a url\" (from env AUTH_URI)"));
+        assert!(error.contains("carrier-pigeon"));
+        assert!(error.contains("`token_store_passphrase` is not set"));
+        assert!(!error.contains("AIza-secret"));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod channel_cache;
+pub mod channel_url;
+pub mod config;
 pub mod oath_2;
+pub mod profile;
+pub mod secret;
 pub mod youtube;
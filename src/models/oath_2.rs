@@ -1,9 +1,289 @@
+use super::secret::Secret;
+use crate::consts;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Access tokens this close to expiry are treated as already expired, so a request started with
+/// them doesn't fail halfway through.
+const EXPIRY_LEEWAY_SECS: u64 = 60;
+
 #[derive(Debug, Clone, Default)]
 pub struct OauthSecrets {
     pub client_id: String,
-    pub client_secret: String,
+    pub client_secret: Secret,
     pub auth_url: String,
     pub token_url: String,
     pub redirect_url: String,
-    pub access_token: String,
+    pub device_auth_url: String,
+    pub revoke_url: String,
+    pub access_token: Secret,
+    pub refresh_token: Option<Secret>,
+    /// Unix timestamp (seconds) at which `access_token` stops being valid.
+    pub expires_at: Option<u64>,
+    /// Scopes granted to `access_token`.
+    pub scopes: Vec<String>,
+}
+
+impl OauthSecrets {
+    /// Copies a cached token into these secrets.
+    pub fn set_token(&mut self, token: StoredToken) {
+        self.access_token = token.access_token;
+        self.refresh_token = token.refresh_token;
+        self.expires_at = token.expires_at;
+        self.scopes = token.scopes;
+    }
+
+    /// Builds secrets from the `client_secret_*.json` file downloaded from the Google Cloud
+    /// console, for either a desktop ("installed") or a "web" client.
+    pub fn from_client_secret_json(json: &str) -> anyhow::Result<Self> {
+        let file: ClientSecretFile = serde_json::from_str(json)?;
+        let Some(client) = file.installed.or(file.web) else {
+            return Err(anyhow!(
+                "Client secret file has neither an `installed` nor a `web` client"
+            ));
+        };
+
+        Ok(Self {
+            client_id: client.client_id,
+            client_secret: client.client_secret.into(),
+            auth_url: client.auth_uri,
+            token_url: client.token_uri,
+            redirect_url: client.redirect_uris.into_iter().next().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
+    /// Overrides individual fields with the settings named in `consts`, where `var` returns
+    /// them.
+    pub fn apply_overrides(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let fields = [
+            (consts::CLIENT_ID, &mut self.client_id),
+            (consts::AUTH_URI, &mut self.auth_url),
+            (consts::TOKEN_URI, &mut self.token_url),
+            (consts::REDIRECT_URI, &mut self.redirect_url),
+            (consts::DEVICE_AUTH_URI, &mut self.device_auth_url),
+            (consts::REVOKE_URI, &mut self.revoke_url),
+        ];
+
+        for (name, field) in fields {
+            if let Some(value) = var(name) {
+                *field = value;
+            }
+        }
+        if let Some(client_secret) = var(consts::CLIENT_SECRET) {
+            self.client_secret = client_secret.into();
+        }
+    }
+
+    /// Returns true when the granted scopes cover every scope in `required`. The full `youtube`
+    /// scope includes everything `youtube.readonly` allows.
+    pub fn has_scopes(&self, required: &[&str]) -> bool {
+        let granted = |scope: &str| self.scopes.iter().any(|granted| granted == scope);
+
+        required.iter().all(|&scope| {
+            granted(scope)
+                || (scope == consts::YOUTUBE_READONLY_SCOPE && granted(consts::YOUTUBE_SCOPE))
+        })
+    }
+
+    /// Describes the token without revealing it, e.g. for printing after authorization.
+    pub fn token_summary(&self) -> String {
+        let expires = match self.expires_at {
+            Some(expires_at) => format!("expires in {}s", expires_at.saturating_sub(unix_now())),
+            None => "has no expiry".to_string(),
+        };
+        let refresh_token = match self.refresh_token {
+            Some(_) => "with",
+            None => "without",
+        };
+
+        format!(
+            "Access token {expires}, {refresh_token} a refresh token, scopes: {}",
+            self.scopes.join(" ")
+        )
+    }
+
+    /// Returns true when there is no access token, or it is (about to be) expired.
+    pub fn is_access_token_expired(&self) -> bool {
+        if self.access_token.is_empty() {
+            return true;
+        }
+
+        match self.expires_at {
+            Some(expires_at) => unix_now() + EXPIRY_LEEWAY_SECS >= expires_at,
+            None => false,
+        }
+    }
+}
+
+/// The `client_secret_*.json` file downloaded from the Google Cloud console.
+#[derive(Deserialize)]
+struct ClientSecretFile {
+    installed: Option<ClientSecretConfig>,
+    web: Option<ClientSecretConfig>,
+}
+
+#[derive(Deserialize)]
+struct ClientSecretConfig {
+    client_id: String,
+    client_secret: String,
+    auth_uri: String,
+    token_uri: String,
+    #[serde(default)]
+    redirect_uris: Vec<String>,
+}
+
+/// The token state persisted between runs for a single account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoredToken {
+    pub access_token: Secret,
+    pub refresh_token: Option<Secret>,
+    pub expires_at: Option<u64>,
+    pub scopes: Vec<String>,
+}
+
+impl From<&OauthSecrets> for StoredToken {
+    fn from(secrets: &OauthSecrets) -> Self {
+        Self {
+            access_token: secrets.access_token.clone(),
+            refresh_token: secrets.refresh_token.clone(),
+            expires_at: secrets.expires_at,
+            scopes: secrets.scopes.clone(),
+        }
+    }
+}
+
+/// How the user completes the OAuth consent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthFlow {
+    /// The browser redirects back to a server listening on the loopback interface.
+    #[default]
+    Loopback,
+    /// The user opens the url on any machine and pastes the redirect url back on stdin.
+    Manual,
+    /// The user enters a short code on another device while we poll the token endpoint.
+    Device,
+}
+
+impl FromStr for AuthFlow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "loopback" => Ok(Self::Loopback),
+            "manual" => Ok(Self::Manual),
+            "device" => Ok(Self::Device),
+            _ => Err(format!(
+                "unknown auth flow `{s}`, expected loopback, manual or device"
+            )),
+        }
+    }
+}
+
+/// Seconds since the unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Where OAuth tokens are cached between runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenBackend {
+    /// A plain JSON file per account, readable by the owner only.
+    #[default]
+    File,
+    /// A file per account encrypted with an age passphrase.
+    Encrypted,
+    /// The Secret Service keyring.
+    Keyring,
+}
+
+impl FromStr for TokenBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "file" => Ok(Self::File),
+            "encrypted" => Ok(Self::Encrypted),
+            "keyring" => Ok(Self::Keyring),
+            _ => Err(format!(
+                "unknown token store `{s}`, expected file, encrypted or keyring"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_client_secret_json_reads_installed_and_web_clients() {
+        let installed = r#"{"installed": {
+            "client_id": "id.apps.googleusercontent.com",
+            "project_id": "project",
+            "auth_uri": "https://accounts.google.com/o/oauth2/auth",
+            "token_uri": "https://oauth2.googleapis.com/token",
+            "client_secret": "secret",
+            "redirect_uris": ["http://localhost"]
+        }}"#;
+        let web = r#"{"web": {
+            "client_id": "web-id",
+            "auth_uri": "https://accounts.google.com/o/oauth2/auth",
+            "token_uri": "https://oauth2.googleapis.com/token",
+            "client_secret": "web-secret",
+            "redirect_uris": ["http://localhost:8080/callback"],
+            "javascript_origins": ["http://localhost:8080"]
+        }}"#;
+
+        let installed = OauthSecrets::from_client_secret_json(installed).unwrap();
+        let web = OauthSecrets::from_client_secret_json(web).unwrap();
+
+        assert_eq!(installed.client_id, "id.apps.googleusercontent.com");
+        assert_eq!(installed.client_secret.expose(), "secret");
+        assert_eq!(installed.token_url, "https://oauth2.googleapis.com/token");
+        assert_eq!(installed.redirect_url, "http://localhost");
+        assert_eq!(web.client_id, "web-id");
+        assert_eq!(web.redirect_url, "http://localhost:8080/callback");
+        assert!(OauthSecrets::from_client_secret_json(r#"{"other": {}}"#).is_err());
+    }
+
+    #[test]
+    fn apply_overrides_replaces_only_set_fields() {
+        let mut secrets = OauthSecrets {
+            client_id: "from-file".to_string(),
+            redirect_url: "http://localhost".to_string(),
+            ..Default::default()
+        };
+
+        secrets.apply_overrides(|name| {
+            (name == consts::REDIRECT_URI).then(|| "http://127.0.0.1:9000".to_string())
+        });
+
+        assert_eq!(secrets.client_id, "from-file");
+        assert_eq!(secrets.redirect_url, "http://127.0.0.1:9000");
+    }
+
+    #[test]
+    fn has_scopes_treats_youtube_as_superset_of_readonly() {
+        let readonly = OauthSecrets {
+            scopes: vec![consts::YOUTUBE_READONLY_SCOPE.to_string()],
+            ..Default::default()
+        };
+        let full = OauthSecrets {
+            scopes: vec![consts::YOUTUBE_SCOPE.to_string()],
+            ..Default::default()
+        };
+
+        assert!(readonly.has_scopes(&[consts::YOUTUBE_READONLY_SCOPE]));
+        assert!(!readonly.has_scopes(&[consts::YOUTUBE_SCOPE]));
+        assert!(full.has_scopes(&[consts::YOUTUBE_READONLY_SCOPE, consts::YOUTUBE_SCOPE]));
+        assert!(!OauthSecrets::default().has_scopes(&[consts::YOUTUBE_READONLY_SCOPE]));
+    }
 }
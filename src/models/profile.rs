@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// A named identity the tool acts as. Each profile has its own cached tokens.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// The channel this profile's token acts as, recorded once it is authorized.
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub channel_title: Option<String>,
+    /// Client secret file used for this profile unless `CLIENT_SECRET_FILE` is set.
+    #[serde(default)]
+    pub client_secret_file: Option<String>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// A short description of the channel this profile is bound to.
+    pub fn binding(&self) -> String {
+        match (&self.channel_title, &self.channel_id) {
+            (Some(title), Some(id)) => format!("{title} ({id})"),
+            (None, Some(id)) => id.clone(),
+            _ => "not authorized yet".to_string(),
+        }
+    }
+}
@@ -1,13 +1,76 @@
+use super::channel_url::ChannelUrl;
+use crate::consts;
 use serde::Deserialize;
 
-/// A model for  a YouTube channel.
+/// A model for  a YouTube channel. Input files may leave out the id and title, as long as the
+/// url identifies the channel.
 #[derive(Clone, Default, Deserialize)]
 pub struct YouTubeChannel {
     pub channel_url: String,
+    #[serde(default)]
     pub channel_id: String,
+    #[serde(default)]
     pub channel_title: String,
 }
 
+impl YouTubeChannel {
+    /// Trims the fields, and takes the channel id from a `/channel/<id>` url when the row has
+    /// none.
+    pub fn normalized(mut self) -> Self {
+        self.channel_url = self.channel_url.trim().to_string();
+        self.channel_id = self.channel_id.trim().to_string();
+        self.channel_title = self.channel_title.trim().to_string();
+
+        if self.channel_id.is_empty() {
+            if let Some(ChannelUrl::Id(id)) = ChannelUrl::parse(&self.channel_url) {
+                self.channel_id = id;
+            }
+        }
+
+        self
+    }
+}
+
+/// One of the account's subscriptions.
+#[derive(Clone, Default)]
+pub struct YouTubeSubscription {
+    /// Id of the subscription resource itself, needed to delete it.
+    pub subscription_id: String,
+    pub channel_id: String,
+    pub channel_title: String,
+    pub description: String,
+    /// When the account subscribed, as an RFC 3339 timestamp.
+    pub subscribed_at: String,
+}
+
+impl YouTubeSubscription {
+    /// The subscribed channel, in the shape input files list channels in.
+    pub fn channel(&self) -> YouTubeChannel {
+        YouTubeChannel {
+            channel_url: format!("https://www.youtube.com/channel/{}", self.channel_id),
+            channel_id: self.channel_id.clone(),
+            channel_title: self.channel_title.clone(),
+        }
+    }
+}
+
+/// What subscribing to the channels of an input file would do, channel by channel.
+#[derive(Default)]
+pub struct YouTubeSubscriptionPlan {
+    /// Channels the account isn't subscribed to yet.
+    pub to_subscribe: Vec<YouTubeChannel>,
+    pub already_subscribed: Vec<YouTubeChannel>,
+    /// Rows repeating a channel listed earlier in the file.
+    pub duplicates: Vec<YouTubeChannel>,
+    /// Rows without a channel id.
+    pub invalid: Vec<YouTubeChannel>,
+    /// Pages of subscriptions read to find the ones already there.
+    pub pages_read: u32,
+    /// Quota units spent looking up the channels of rows that only had a url, while making the
+    /// plan. Carrying it out finds them in the channel cache, so they aren't spent again.
+    pub lookup_cost: u32,
+}
+
 pub struct FailedYoutubeSubscription {
     pub channel_url: String,
     pub channel_id: String,
@@ -18,9 +81,38 @@ pub struct FailedYoutubeSubscription {
 /// # Fields
 /// `expected`: The number of channel subscriptions expected.
 /// `successful`: The number of successful channel subscriptions.
+/// `already_subscribed`: Channels skipped because the account was subscribed to them already.
+/// `not_subscribed`: Channels skipped when unsubscribing, because the account wasn't subscribed.
 #[derive(Default)]
 pub struct YouTubeSubscriptionResult {
     pub expected: i32,
     pub successful: i32,
     pub failed: Vec<FailedYoutubeSubscription>,
+    pub already_subscribed: Vec<YouTubeChannel>,
+    pub not_subscribed: Vec<YouTubeChannel>,
+}
+
+impl YouTubeSubscriptionPlan {
+    /// Quota units carrying out the plan costs, including reading the subscriptions again.
+    pub fn quota_cost(&self) -> u32 {
+        self.to_subscribe.len() as u32 * consts::SUBSCRIBE_QUOTA_COST
+            + self.pages_read * consts::LIST_QUOTA_COST
+    }
+}
+
+/// What converging the account towards an input file would do: the file's plan, plus the
+/// subscriptions the file doesn't list.
+#[derive(Default)]
+pub struct YouTubeSyncPlan {
+    pub subscribe: YouTubeSubscriptionPlan,
+    pub to_unsubscribe: Vec<YouTubeSubscription>,
+}
+
+impl YouTubeSyncPlan {
+    /// Quota units carrying out the plan costs, including listing the subscriptions once to make
+    /// it; applying the plan doesn't list them again.
+    pub fn quota_cost(&self) -> u32 {
+        self.subscribe.quota_cost()
+            + self.to_unsubscribe.len() as u32 * consts::UNSUBSCRIBE_QUOTA_COST
+    }
 }
@@ -1,3 +1,9 @@
+pub(crate) mod channel_cache;
 pub mod csv_reader;
 pub(crate) mod csv_writer;
+pub(crate) mod encrypted_token_store;
+pub(crate) mod file_token_store;
 pub(crate) mod interfaces;
+pub(crate) mod keyring_token_store;
+pub(crate) mod paths;
+pub(crate) mod profile_store;
@@ -0,0 +1,257 @@
+use super::interfaces::t_channel_cache::TChannelCache;
+use crate::models::{
+    channel_cache::{ChannelCacheEntry, ChannelCacheKind},
+    oath_2::unix_now,
+    youtube::YouTubeChannel,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Keeps the channel lookups in a single JSON file, by default `<cache dir>/channels.json`.
+pub struct ChannelCache {
+    path: PathBuf,
+    id_ttl: Duration,
+    metadata_ttl: Duration,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// Channel ids, keyed by canonical url.
+    #[serde(default)]
+    ids: BTreeMap<String, CachedId>,
+    /// Channel metadata, keyed by channel id.
+    #[serde(default)]
+    channels: BTreeMap<String, CachedChannel>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedId {
+    channel_id: String,
+    cached_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedChannel {
+    channel_title: String,
+    cached_at: u64,
+}
+
+impl ChannelCache {
+    pub fn new(path: PathBuf, id_ttl: Duration, metadata_ttl: Duration) -> Self {
+        Self {
+            path,
+            id_ttl,
+            metadata_ttl,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> anyhow::Result<CacheFile> {
+        if !self.path.exists() {
+            return Ok(CacheFile::default());
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Could not read {}", self.path.display()))?;
+        serde_json::from_str(&contents).with_context(|| {
+            format!(
+                "{} is corrupt, run `cache clear` to start over",
+                self.path.display()
+            )
+        })
+    }
+
+    fn write(&self, file: &CacheFile) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+
+        fs::write(&self.path, serde_json::to_string_pretty(file)?)
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+/// Whether an entry cached at `cached_at` is older than `ttl` at `now`.
+fn expired(cached_at: u64, ttl: Duration, now: u64) -> bool {
+    now.saturating_sub(cached_at) >= ttl.as_secs()
+}
+
+impl TChannelCache for ChannelCache {
+    fn channel_id(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let now = unix_now();
+        Ok(self
+            .read()?
+            .ids
+            .remove(key)
+            .filter(|cached| !expired(cached.cached_at, self.id_ttl, now))
+            .map(|cached| cached.channel_id))
+    }
+
+    fn channel(&self, channel_id: &str) -> anyhow::Result<Option<YouTubeChannel>> {
+        let now = unix_now();
+        Ok(self
+            .read()?
+            .channels
+            .remove(channel_id)
+            .filter(|cached| !expired(cached.cached_at, self.metadata_ttl, now))
+            .map(|cached| YouTubeChannel {
+                channel_url: format!("https://www.youtube.com/channel/{channel_id}"),
+                channel_id: channel_id.to_string(),
+                channel_title: cached.channel_title,
+            }))
+    }
+
+    fn save(&self, key: &str, channel: &YouTubeChannel) -> anyhow::Result<()> {
+        let mut file = self.read()?;
+        let now = unix_now();
+
+        file.ids.insert(
+            key.to_string(),
+            CachedId {
+                channel_id: channel.channel_id.clone(),
+                cached_at: now,
+            },
+        );
+        file.channels.insert(
+            channel.channel_id.clone(),
+            CachedChannel {
+                channel_title: channel.channel_title.clone(),
+                cached_at: now,
+            },
+        );
+
+        self.write(&file)
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<ChannelCacheEntry>> {
+        let file = self.read()?;
+        let now = unix_now();
+
+        let ids = file.ids.into_iter().map(|(key, cached)| ChannelCacheEntry {
+            kind: ChannelCacheKind::Id,
+            key,
+            value: cached.channel_id,
+            cached_at: cached.cached_at,
+            expired: expired(cached.cached_at, self.id_ttl, now),
+        });
+        let channels = file
+            .channels
+            .into_iter()
+            .map(|(key, cached)| ChannelCacheEntry {
+                kind: ChannelCacheKind::Metadata,
+                key,
+                value: cached.channel_title,
+                cached_at: cached.cached_at,
+                expired: expired(cached.cached_at, self.metadata_ttl, now),
+            });
+
+        Ok(ids.chain(channels).collect())
+    }
+
+    fn clear(&self, expired_only: bool) -> anyhow::Result<usize> {
+        if !expired_only {
+            // A corrupt file is cleared too, so the count can't always be known.
+            let count = self.read().map(|file| file.ids.len() + file.channels.len());
+            if self.path.exists() {
+                fs::remove_file(&self.path)
+                    .with_context(|| format!("Could not delete {}", self.path.display()))?;
+            }
+            return Ok(count.unwrap_or_default());
+        }
+
+        let mut file = self.read()?;
+        let now = unix_now();
+        let count = file.ids.len() + file.channels.len();
+        file.ids
+            .retain(|_, cached| !expired(cached.cached_at, self.id_ttl, now));
+        file.channels
+            .retain(|_, cached| !expired(cached.cached_at, self.metadata_ttl, now));
+        let removed = count - file.ids.len() - file.channels.len();
+
+        if removed > 0 {
+            self.write(&file)?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: &str, title: &str) -> YouTubeChannel {
+        YouTubeChannel {
+            channel_url: String::new(),
+            channel_id: id.to_string(),
+            channel_title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn save_then_look_up_until_expired() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("channels.json");
+        let day = Duration::from_secs(24 * 60 * 60);
+        let sut = ChannelCache::new(path.clone(), day, day);
+        let stale = ChannelCache::new(path, Duration::ZERO, day);
+
+        // act
+        sut.save("https://www.youtube.com/@handle", &channel("UC1", "Title"))
+            .unwrap();
+
+        // assert
+        assert_eq!(
+            sut.channel_id("https://www.youtube.com/@handle")
+                .unwrap()
+                .as_deref(),
+            Some("UC1")
+        );
+        assert_eq!(sut.channel("UC1").unwrap().unwrap().channel_title, "Title");
+        assert_eq!(
+            sut.channel_id("https://www.youtube.com/@other").unwrap(),
+            None
+        );
+        assert_eq!(
+            stale.channel_id("https://www.youtube.com/@handle").unwrap(),
+            None
+        );
+        assert!(stale.channel("UC1").unwrap().is_some());
+    }
+
+    #[test]
+    fn clear_removes_expired_entries_or_everything() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("channels.json");
+        let day = Duration::from_secs(24 * 60 * 60);
+        let sut = ChannelCache::new(path.clone(), day, day);
+        sut.save("a", &channel("UC1", "One")).unwrap();
+        sut.save("b", &channel("UC2", "Two")).unwrap();
+        let ids_expired = ChannelCache::new(path, Duration::ZERO, day);
+
+        // act
+        let expired = ids_expired.clear(true).unwrap();
+        let entries = sut.entries().unwrap();
+        let all = sut.clear(false).unwrap();
+
+        // assert
+        assert_eq!(expired, 2);
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.kind == ChannelCacheKind::Metadata));
+        assert_eq!(all, 2);
+        assert!(sut.entries().unwrap().is_empty());
+    }
+}
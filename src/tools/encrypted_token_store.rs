@@ -0,0 +1,107 @@
+use super::{
+    file_token_store::{account_path, write_private},
+    interfaces::t_token_store::TTokenStore,
+    paths,
+};
+use crate::models::oath_2::StoredToken;
+use age::{
+    scrypt::{Identity, Recipient},
+    secrecy::SecretString,
+};
+use anyhow::{anyhow, Context};
+use std::{fs, path::PathBuf};
+
+/// Stores each account's token as a file under `<config dir>/tokens`, encrypted with an age
+/// passphrase.
+pub struct EncryptedTokenStore {
+    dir: PathBuf,
+    passphrase: SecretString,
+}
+
+impl EncryptedTokenStore {
+    pub fn new(dir: PathBuf, passphrase: String) -> Self {
+        Self {
+            dir,
+            passphrase: SecretString::from(passphrase),
+        }
+    }
+
+    /// Stores tokens in the default location.
+    pub fn with_passphrase(passphrase: String) -> Self {
+        Self::new(paths::config_dir().join("tokens"), passphrase)
+    }
+
+    fn path(&self, account: &str) -> anyhow::Result<PathBuf> {
+        account_path(&self.dir, account, "age")
+    }
+}
+
+impl TTokenStore for EncryptedTokenStore {
+    fn load(&self, account: &str) -> anyhow::Result<Option<StoredToken>> {
+        let path = self.path(account)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let encrypted = fs::read(&path)
+            .with_context(|| format!("Could not read token cache {}", path.display()))?;
+        let json = age::decrypt(&Identity::new(self.passphrase.clone()), &encrypted)
+            .map_err(|e| anyhow!("Could not decrypt token cache {}: {e}", path.display()))?;
+        let token = serde_json::from_slice(&json)
+            .with_context(|| format!("Token cache {} is corrupt", path.display()))?;
+
+        Ok(Some(token))
+    }
+
+    fn save(&self, account: &str, token: &StoredToken) -> anyhow::Result<()> {
+        let path = self.path(account)?;
+        let json = serde_json::to_vec(token)?;
+        let encrypted = age::encrypt(&Recipient::new(self.passphrase.clone()), &json)
+            .map_err(|e| anyhow!("Could not encrypt the token: {e}"))?;
+
+        write_private(&path, &encrypted)
+    }
+
+    fn delete(&self, account: &str) -> anyhow::Result<()> {
+        let path = self.path(account)?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not delete {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::secret::Secret;
+
+    #[test]
+    fn save_then_load_round_trips_without_plaintext_on_disk() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = EncryptedTokenStore::new(dir.path().to_path_buf(), "passphrase".to_string());
+        let token = StoredToken {
+            access_token: "access".into(),
+            refresh_token: Some("refresh".into()),
+            ..Default::default()
+        };
+
+        // act
+        sut.save("work", &token).unwrap();
+        let loaded = sut.load("work").unwrap().unwrap();
+        let wrong_passphrase =
+            EncryptedTokenStore::new(dir.path().to_path_buf(), "guess".to_string()).load("work");
+
+        // assert
+        assert_eq!(
+            loaded.refresh_token.as_ref().map(Secret::expose),
+            Some("refresh")
+        );
+        let on_disk = fs::read(dir.path().join("work.age")).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("refresh"));
+        assert!(wrong_passphrase.is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use super::{interfaces::t_token_store::TTokenStore, paths};
+use crate::models::oath_2::StoredToken;
+use anyhow::{anyhow, Context};
+use std::{
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Stores each account's token as a JSON file under `<config dir>/tokens`.
+pub struct FileTokenStore {
+    dir: PathBuf,
+}
+
+impl Default for FileTokenStore {
+    fn default() -> Self {
+        Self::new(paths::config_dir().join("tokens"))
+    }
+}
+
+impl FileTokenStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, account: &str) -> anyhow::Result<PathBuf> {
+        account_path(&self.dir, account, "json")
+    }
+}
+
+impl TTokenStore for FileTokenStore {
+    fn load(&self, account: &str) -> anyhow::Result<Option<StoredToken>> {
+        let path = self.path(account)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read token cache {}", path.display()))?;
+        let token = serde_json::from_str(&contents)
+            .with_context(|| format!("Token cache {} is corrupt", path.display()))?;
+
+        Ok(Some(token))
+    }
+
+    fn save(&self, account: &str, token: &StoredToken) -> anyhow::Result<()> {
+        let path = self.path(account)?;
+        write_private(&path, serde_json::to_string_pretty(token)?.as_bytes())
+    }
+
+    fn delete(&self, account: &str) -> anyhow::Result<()> {
+        let path = self.path(account)?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not delete {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The file `account` is stored in under `dir`. Account names are checked, so they can't
+/// point outside of `dir`.
+pub(super) fn account_path(dir: &Path, account: &str, extension: &str) -> anyhow::Result<PathBuf> {
+    let valid = !account.is_empty()
+        && account
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+        && !account.starts_with('.');
+    if !valid {
+        return Err(anyhow!("Invalid account name `{account}`"));
+    }
+
+    Ok(dir.join(format!("{account}.{extension}")))
+}
+
+/// Writes `contents` to `path`, creating its directory first. The contents go to a temporary
+/// file next to it that is renamed over `path`, so a file created with looser permissions is
+/// replaced rather than reused, and a failed write leaves the old token in place.
+pub(super) fn write_private(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    // Left over from an interrupted write.
+    match fs::remove_file(&temp_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Could not delete {}", temp_path.display()))
+        }
+        _ => {}
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // The refresh token grants long-lived access, keep it readable by the owner only.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e).with_context(|| format!("Could not write token cache {}", path.display()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::secret::Secret;
+
+    #[test]
+    fn save_then_load_round_trips() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = FileTokenStore::new(dir.path().join("tokens"));
+        let token = StoredToken {
+            access_token: "access".into(),
+            refresh_token: Some("refresh".into()),
+            expires_at: Some(42),
+            scopes: vec!["scope".to_string()],
+        };
+
+        // act
+        sut.save("work", &token).unwrap();
+        let loaded = sut.load("work").unwrap().unwrap();
+
+        // assert
+        assert_eq!(loaded.access_token.expose(), "access");
+        assert_eq!(
+            loaded.refresh_token.as_ref().map(Secret::expose),
+            Some("refresh")
+        );
+        assert_eq!(loaded.expires_at, Some(42));
+        assert!(sut.load("personal").unwrap().is_none());
+    }
+
+    #[test]
+    fn delete_removes_token() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = FileTokenStore::new(dir.path().to_path_buf());
+        sut.save("work", &StoredToken::default()).unwrap();
+
+        // act
+        sut.delete("work").unwrap();
+
+        // assert
+        assert!(sut.load("work").unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_restricts_an_existing_file_to_the_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = FileTokenStore::new(dir.path().to_path_buf());
+        let path = dir.path().join("work.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        // act
+        sut.save("work", &StoredToken::default()).unwrap();
+
+        // assert
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn rejects_account_names_that_escape_the_directory() {
+        let sut = FileTokenStore::new(PathBuf::from("/tmp"));
+
+        assert!(sut.load("../etc/passwd").is_err());
+        assert!(sut.load("").is_err());
+    }
+}
@@ -1,2 +1,5 @@
+pub(crate) mod t_channel_cache;
 pub(crate) mod t_csv_reader;
 pub(crate) mod t_csv_writer;
+pub(crate) mod t_profile_store;
+pub(crate) mod t_token_store;
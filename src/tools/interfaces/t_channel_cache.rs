@@ -0,0 +1,19 @@
+use crate::models::{channel_cache::ChannelCacheEntry, youtube::YouTubeChannel};
+
+/// Remembers channel lookups between runs, so resolving the same urls again costs no quota.
+pub trait TChannelCache {
+    /// The id of the channel the url `key` resolved to, unless it is not cached or expired.
+    fn channel_id(&self, key: &str) -> anyhow::Result<Option<String>>;
+
+    /// The cached metadata of the channel `channel_id`, unless it is not cached or expired.
+    fn channel(&self, channel_id: &str) -> anyhow::Result<Option<YouTubeChannel>>;
+
+    /// Records that the url `key` resolved to `channel`, along with the channel's metadata.
+    fn save(&self, key: &str, channel: &YouTubeChannel) -> anyhow::Result<()>;
+
+    /// Every cached entry, expired ones included.
+    fn entries(&self) -> anyhow::Result<Vec<ChannelCacheEntry>>;
+
+    /// Removes every entry, or only the expired ones. Returns how many were removed.
+    fn clear(&self, expired_only: bool) -> anyhow::Result<usize>;
+}
@@ -0,0 +1,14 @@
+use crate::models::profile::Profile;
+
+/// Persists the named account profiles.
+pub trait TProfileStore {
+    fn list(&self) -> anyhow::Result<Vec<Profile>>;
+
+    fn get(&self, name: &str) -> anyhow::Result<Option<Profile>>;
+
+    /// Adds `profile`, or replaces the profile with the same name.
+    fn save(&self, profile: &Profile) -> anyhow::Result<()>;
+
+    /// Removes the profile named `name`. Returns false when there was no such profile.
+    fn remove(&self, name: &str) -> anyhow::Result<bool>;
+}
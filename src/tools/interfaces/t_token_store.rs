@@ -0,0 +1,11 @@
+use crate::models::oath_2::StoredToken;
+
+/// Persists OAuth tokens between runs, keyed by account name.
+pub trait TTokenStore {
+    /// Returns the token cached for `account`, or `None` if nothing was stored yet.
+    fn load(&self, account: &str) -> anyhow::Result<Option<StoredToken>>;
+
+    fn save(&self, account: &str, token: &StoredToken) -> anyhow::Result<()>;
+
+    fn delete(&self, account: &str) -> anyhow::Result<()>;
+}
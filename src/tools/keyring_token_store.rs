@@ -0,0 +1,172 @@
+use super::interfaces::t_token_store::TTokenStore;
+use crate::{consts, models::oath_2::StoredToken};
+use anyhow::{anyhow, Context};
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// Stores each account's token in the Secret Service keyring (GNOME Keyring, KWallet, ...)
+/// through `secret-tool`, which talks to it over D-Bus.
+pub struct KeyringTokenStore {
+    /// The `secret-tool` binary, or anything that accepts the same arguments.
+    program: PathBuf,
+}
+
+impl Default for KeyringTokenStore {
+    fn default() -> Self {
+        Self::new(PathBuf::from(consts::DEFAULT_SECRET_TOOL))
+    }
+}
+
+impl KeyringTokenStore {
+    pub fn new(program: PathBuf) -> Self {
+        Self { program }
+    }
+
+    /// Runs `secret-tool <action> [args] service <app> account <account>`, with `input` on stdin.
+    fn run(
+        &self,
+        action: &str,
+        args: &[&str],
+        account: &str,
+        input: Option<&[u8]>,
+    ) -> anyhow::Result<Output> {
+        let mut child = Command::new(&self.program)
+            .arg(action)
+            .args(args)
+            .args(["service", consts::APP_NAME, "account", account])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not run {}", self.program.display()))?;
+
+        if let Some(input) = input {
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(input)?;
+        }
+
+        Ok(child.wait_with_output()?)
+    }
+}
+
+impl TTokenStore for KeyringTokenStore {
+    fn load(&self, account: &str) -> anyhow::Result<Option<StoredToken>> {
+        let output = self.run("lookup", &[], account, None)?;
+
+        // A lookup that finds nothing fails without saying why.
+        if !output.status.success() && output.stdout.is_empty() && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Could not read the token from the keyring: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let token = serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("The keyring entry of account `{account}` is corrupt"))?;
+
+        Ok(Some(token))
+    }
+
+    fn save(&self, account: &str, token: &StoredToken) -> anyhow::Result<()> {
+        let label = format!("--label={} ({account})", consts::APP_NAME);
+        let json = serde_json::to_vec(token)?;
+        let output = self.run("store", &[&label], account, Some(&json))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Could not store the token in the keyring: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> anyhow::Result<()> {
+        let output = self.run("clear", &[], account, None)?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Could not delete the token from the keyring: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::models::secret::Secret;
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+    /// A stand-in for `secret-tool` that keeps each secret in a file under `dir`, named after
+    /// its attributes.
+    fn fake_secret_tool(dir: &Path) -> PathBuf {
+        let script = format!(
+            r#"#!/bin/sh
+action=$1; shift
+key=""
+for arg in "$@"; do
+  case $arg in --*) ;; *) key="$key-$arg" ;; esac
+done
+file="{}/$key"
+case $action in
+  store) cat > "$file" ;;
+  lookup) [ -f "$file" ] || exit 1; cat "$file" ;;
+  clear) rm -f "$file" ;;
+esac
+"#,
+            dir.display()
+        );
+        let program = dir.join("secret-tool");
+        fs::write(&program, script).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        program
+    }
+
+    #[test]
+    fn save_load_and_delete_go_through_secret_tool() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = KeyringTokenStore::new(fake_secret_tool(dir.path()));
+        let token = StoredToken {
+            access_token: "access".into(),
+            refresh_token: Some("refresh".into()),
+            ..Default::default()
+        };
+
+        // act
+        sut.save("work", &token).unwrap();
+        let loaded = sut.load("work").unwrap();
+        let other = sut.load("personal").unwrap();
+        sut.delete("work").unwrap();
+        let deleted = sut.load("work").unwrap();
+
+        // assert
+        assert_eq!(
+            loaded.unwrap().refresh_token.as_ref().map(Secret::expose),
+            Some("refresh")
+        );
+        assert!(other.is_none());
+        assert!(deleted.is_none());
+    }
+
+    #[test]
+    fn missing_secret_tool_is_an_error() {
+        let sut = KeyringTokenStore::new(PathBuf::from("/nonexistent/secret-tool"));
+
+        assert!(sut.load("work").is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use std::{env, path::PathBuf};
+
+use crate::consts;
+
+/// Directory holding this tool's state, following the XDG base directory spec:
+/// `$XDG_CONFIG_HOME/youtube-auto-sub`, falling back to `~/.config/youtube-auto-sub`.
+pub fn config_dir() -> PathBuf {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        // The spec says relative paths are invalid and should be ignored.
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    base.join(consts::APP_NAME)
+}
+
+/// Directory holding data this tool can rebuild, following the XDG base directory spec:
+/// `$XDG_CACHE_HOME/youtube-auto-sub`, falling back to `~/.cache/youtube-auto-sub`.
+pub fn cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    base.join(consts::APP_NAME)
+}
@@ -0,0 +1,115 @@
+use super::{interfaces::t_profile_store::TProfileStore, paths};
+use crate::models::profile::Profile;
+use anyhow::Context;
+use std::{fs, path::PathBuf};
+
+/// Stores all profiles in a single JSON file, `<config dir>/profiles.json`.
+pub struct ProfileStore {
+    path: PathBuf,
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        Self::new(paths::config_dir().join("profiles.json"))
+    }
+}
+
+impl ProfileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn write(&self, profiles: &[Profile]) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+
+        fs::write(&self.path, serde_json::to_string_pretty(profiles)?)
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+impl TProfileStore for ProfileStore {
+    fn list(&self) -> anyhow::Result<Vec<Profile>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Could not read {}", self.path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("{} is corrupt", self.path.display()))
+    }
+
+    fn get(&self, name: &str) -> anyhow::Result<Option<Profile>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|profile| profile.name == name))
+    }
+
+    fn save(&self, profile: &Profile) -> anyhow::Result<()> {
+        let mut profiles = self.list()?;
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile.clone(),
+            None => profiles.push(profile.clone()),
+        }
+
+        self.write(&profiles)
+    }
+
+    fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        let mut profiles = self.list()?;
+        let count = profiles.len();
+        profiles.retain(|profile| profile.name != name);
+        if profiles.len() == count {
+            return Ok(false);
+        }
+
+        self.write(&profiles)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_replaces_profiles_by_name() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = ProfileStore::new(dir.path().join("profiles.json"));
+        let mut brand = Profile::new("brand");
+
+        // act
+        sut.save(&Profile::new("personal")).unwrap();
+        sut.save(&brand).unwrap();
+        brand.channel_id = Some("UC123".to_string());
+        sut.save(&brand).unwrap();
+
+        // assert
+        let names: Vec<String> = sut.list().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["personal", "brand"]);
+        let brand = sut.get("brand").unwrap().unwrap();
+        assert_eq!(brand.channel_id.as_deref(), Some("UC123"));
+    }
+
+    #[test]
+    fn remove_reports_missing_profiles() {
+        // arrange
+        let dir = tempfile::tempdir().unwrap();
+        let sut = ProfileStore::new(dir.path().join("profiles.json"));
+        sut.save(&Profile::new("personal")).unwrap();
+
+        // act
+        let removed = sut.remove("personal").unwrap();
+        let removed_again = sut.remove("personal").unwrap();
+
+        // assert
+        assert!(removed);
+        assert!(!removed_again);
+        assert!(sut.list().unwrap().is_empty());
+    }
+}